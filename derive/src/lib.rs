@@ -0,0 +1,356 @@
+//! Derive macros for [a653rs-postcard](https://docs.rs/a653rs-postcard)
+//!
+//! Use the re-exports of `a653rs_postcard::ports` and `a653rs_postcard::icd` instead of depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Field, Fields, LitInt, LitStr};
+
+/// Generate a `create` constructor creating every port of a partition interface
+///
+/// Every field needs to be annotated with either
+/// - `#[queuing(name = "...", depth = ...)]`, optionally with `discipline = "fifo"` or `"priority"`,
+/// - or `#[sampling(name = "...")]` with `refresh_ms = ...` for destinations,
+///   which is checked at compile time.
+///
+/// Both optionally take `codec = ...`, an expression evaluating to the codec of the port,
+/// and `size = ...`, the message size of the port.
+/// The message size defaults to the maximum encoded size of the message type
+/// and must not be smaller than it, which is checked at compile time.
+///
+/// See `a653rs_postcard::ports::PartitionPorts` for details.
+#[proc_macro_derive(PartitionPorts, attributes(queuing, sampling))]
+pub fn derive_partition_ports(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_ports(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implement `PartitionIcd`, describing every port declared for [`PartitionPorts`]
+///
+/// Requires the `icd` feature of a653rs-postcard.
+/// See `a653rs_postcard::icd::PartitionIcd` for details.
+#[proc_macro_derive(PartitionIcd, attributes(queuing, sampling))]
+pub fn derive_partition_icd(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_icd(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Named fields of the struct `input`
+fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> Result<impl Iterator<Item = &'a Field>, Error> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter()),
+            _ => Err(Error::new(
+                input.ident.span(),
+                format!("{derive} requires named fields"),
+            )),
+        },
+        _ => Err(Error::new(
+            input.ident.span(),
+            format!("{derive} can only be derived for structs"),
+        )),
+    }
+}
+
+fn expand_ports(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let mut bounds = Vec::new();
+    let mut inits = Vec::new();
+    for field in named_fields(&input, "PartitionPorts")? {
+        let (bound, init, _) = expand_field(field)?;
+        bounds.push(bound);
+        inits.push(init);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Create every port of the partition interface
+            ///
+            /// Fails if any port fails to be created,
+            /// or with `Error::InvalidConfig` if a port name is too long.
+            pub fn create<__H>(
+                ctx: &mut ::a653rs_postcard::ports::__private::StartContext<__H>,
+            ) -> ::core::result::Result<Self, ::a653rs_postcard::ports::__private::Error>
+            where
+                #predicates
+                #(#bounds,)*
+            {
+                ::core::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+fn expand_icd(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let mut types = Vec::new();
+    let mut descriptions = Vec::new();
+    for field in named_fields(&input, "PartitionIcd")? {
+        let (_, _, description) = expand_field(field)?;
+        types.push(&field.ty);
+        descriptions.push(description);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    Ok(quote! {
+        impl #impl_generics ::a653rs_postcard::icd::PartitionIcd for #ident #ty_generics
+        where
+            #predicates
+            #(#types: ::a653rs_postcard::icd::DescribePort,)*
+        {
+            fn icd() -> ::a653rs_postcard::icd::Icd {
+                ::a653rs_postcard::icd::Icd::new([
+                    #(#descriptions,)*
+                ])
+            }
+        }
+    })
+}
+
+/// Port configuration of a single field
+enum Port {
+    Queuing {
+        name: LitStr,
+        depth: LitInt,
+        discipline: &'static str,
+        size: Option<LitInt>,
+        codec: Option<Expr>,
+    },
+    Sampling {
+        name: LitStr,
+        refresh_ms: Option<LitInt>,
+        size: Option<LitInt>,
+        codec: Option<Expr>,
+    },
+}
+
+/// Bound, initializer and description of the port of `field`
+fn expand_field(field: &Field) -> Result<(TokenStream2, TokenStream2, TokenStream2), Error> {
+    let mut port = None;
+    for attr in &field.attrs {
+        let parsed = if attr.path().is_ident("queuing") {
+            parse_queuing(attr)?
+        } else if attr.path().is_ident("sampling") {
+            parse_sampling(attr)?
+        } else {
+            continue;
+        };
+        if port.replace(parsed).is_some() {
+            return Err(Error::new(attr.span(), "duplicate port attribute"));
+        }
+    }
+    let Some(port) = port else {
+        return Err(Error::new(
+            field.span(),
+            "missing #[queuing(...)] or #[sampling(...)] attribute",
+        ));
+    };
+
+    let ident = &field.ident;
+    let ty = &field.ty;
+    let span = ty.span();
+    let ports = quote!(::a653rs_postcard::ports);
+    let icd = quote!(::a653rs_postcard::icd);
+    let with_codec = |codec: Option<Expr>| codec.map(|codec| quote!(.with_codec(#codec)));
+    // Message size and the ICD override of the declared size, checked against `MAX_SIZE` of `create`
+    let message_size = |size: Option<LitInt>, create: TokenStream2| -> Result<_, Error> {
+        let max_size = quote!(<#ty as #ports::#create<__H>>::MAX_SIZE);
+        Ok(match size {
+            Some(size) => {
+                let value = size.base10_parse::<u32>()?;
+                if value == 0 {
+                    return Err(Error::new(size.span(), "`size` must not be zero"));
+                }
+                let value = value as usize;
+                (
+                    quote! {{
+                        const {
+                            ::core::assert!(
+                                #value >= #max_size,
+                                "`size` is smaller than the maximum encoded size of the message type",
+                            )
+                        };
+                        #ports::__private::message_size(#value)?
+                    }},
+                    Some(quote!(.with_max_size(#value))),
+                )
+            }
+            None => (
+                quote! {{
+                    const {
+                        ::core::assert!(
+                            #max_size > 0,
+                            "zero-sized message type, declare the message size using `size = ...`",
+                        )
+                    };
+                    #ports::__private::message_size(#max_size)?
+                }},
+                None,
+            ),
+        })
+    };
+    Ok(match port {
+        Port::Queuing {
+            name,
+            depth,
+            discipline,
+            size,
+            codec,
+        } => {
+            let with_codec = with_codec(codec);
+            let (size, with_size) = message_size(size, quote!(CreateQueuingPort))?;
+            let variant = match discipline {
+                "priority" => quote!(Priority),
+                _ => quote!(Fifo),
+            };
+            let discipline = quote!(#ports::__private::QueuingDiscipline::#variant);
+            (
+                quote_spanned!(span=> #ty: #ports::CreateQueuingPort<__H>),
+                quote! {
+                    #ident: <#ty as #ports::CreateQueuingPort<__H>>::create_queuing_port(
+                        ctx,
+                        #ports::__private::port_name(#name)?,
+                        #size,
+                        #depth,
+                        #discipline,
+                    )?
+                    #with_codec
+                },
+                quote!(#icd::PortDescription::queuing::<#ty>(#name, #depth, #discipline)#with_size),
+            )
+        }
+        Port::Sampling {
+            name,
+            refresh_ms,
+            size,
+            codec,
+        } => {
+            let with_codec = with_codec(codec);
+            let (size, with_size) = message_size(size, quote!(CreateSamplingPort))?;
+            let (refresh, refresh_ms) = match refresh_ms {
+                Some(ms) => (
+                    quote!(::core::time::Duration::from_millis(#ms)),
+                    quote!(::core::option::Option::Some(#ms)),
+                ),
+                None => (
+                    quote! {{
+                        const {
+                            ::core::assert!(
+                                !<#ty as #ports::CreateSamplingPort<__H>>::REQUIRES_REFRESH,
+                                "sampling destination requires `refresh_ms = ...`",
+                            )
+                        };
+                        ::core::time::Duration::ZERO
+                    }},
+                    quote! {{
+                        const {
+                            ::core::assert!(
+                                !::core::matches!(
+                                    <#ty as #icd::DescribePort>::KIND,
+                                    #icd::PortKind::SamplingDestination,
+                                ),
+                                "sampling destination requires `refresh_ms = ...`",
+                            )
+                        };
+                        ::core::option::Option::None
+                    }},
+                ),
+            };
+            (
+                quote_spanned!(span=> #ty: #ports::CreateSamplingPort<__H>),
+                quote! {
+                    #ident: <#ty as #ports::CreateSamplingPort<__H>>::create_sampling_port(
+                        ctx,
+                        #ports::__private::port_name(#name)?,
+                        #size,
+                        #refresh,
+                    )?
+                    #with_codec
+                },
+                quote!(#icd::PortDescription::sampling::<#ty>(#name, #refresh_ms)#with_size),
+            )
+        }
+    })
+}
+
+fn parse_queuing(attr: &syn::Attribute) -> Result<Port, Error> {
+    let mut name = None;
+    let mut depth = None;
+    let mut discipline = "fifo";
+    let mut size = None;
+    let mut codec = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+        } else if meta.path.is_ident("depth") {
+            depth = Some(meta.value()?.parse::<LitInt>()?);
+        } else if meta.path.is_ident("discipline") {
+            let value = meta.value()?.parse::<LitStr>()?;
+            discipline = match value.value().as_str() {
+                "fifo" => "fifo",
+                "priority" => "priority",
+                _ => {
+                    return Err(Error::new(
+                        value.span(),
+                        "expected \"fifo\" or \"priority\"",
+                    ))
+                }
+            };
+        } else if meta.path.is_ident("size") {
+            size = Some(meta.value()?.parse::<LitInt>()?);
+        } else if meta.path.is_ident("codec") {
+            codec = Some(meta.value()?.parse::<Expr>()?);
+        } else {
+            return Err(meta.error("unknown queuing port option"));
+        }
+        Ok(())
+    })?;
+    Ok(Port::Queuing {
+        name: name.ok_or_else(|| Error::new(attr.span(), "missing `name`"))?,
+        depth: depth.ok_or_else(|| Error::new(attr.span(), "missing `depth`"))?,
+        discipline,
+        size,
+        codec,
+    })
+}
+
+fn parse_sampling(attr: &syn::Attribute) -> Result<Port, Error> {
+    let mut name = None;
+    let mut refresh_ms = None;
+    let mut size = None;
+    let mut codec = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+        } else if meta.path.is_ident("refresh_ms") {
+            refresh_ms = Some(meta.value()?.parse::<LitInt>()?);
+        } else if meta.path.is_ident("size") {
+            size = Some(meta.value()?.parse::<LitInt>()?);
+        } else if meta.path.is_ident("codec") {
+            codec = Some(meta.value()?.parse::<Expr>()?);
+        } else {
+            return Err(meta.error("unknown sampling port option"));
+        }
+        Ok(())
+    })?;
+    Ok(Port::Sampling {
+        name: name.ok_or_else(|| Error::new(attr.span(), "missing `name`"))?,
+        refresh_ms,
+        size,
+        codec,
+    })
+}
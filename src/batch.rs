@@ -0,0 +1,384 @@
+//! Packing several values into a single queuing port message
+//!
+//! A batch message consists of length-delimited items.
+//! Every item is prefixed with its serialized length, encoded as a postcard `u32` varint.
+
+use core::marker::PhantomData;
+
+use a653rs::prelude::*;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+
+/// Postcard batching extension trait for queuing port sender
+pub trait QueuingPortBatchSenderExt {
+    /// Send as many items of `items` as fit into a single message
+    /// using an a653rs [`QueuingPortSender`]
+    ///
+    /// Requires a buffer `buf` for serialization.
+    /// Returns the number of packed items, which are the first items of `items`.
+    /// Nothing is sent if `items` is empty.
+    /// Fails with [`postcard::Error::SerializeBufferFull`] if not even the first item fits.
+    /// If an item fails to serialize, the items before it are still sent
+    /// and their number is returned with the error.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let events = [1u32; 300];
+    /// let mut buf = [0; 500];
+    /// let mut pending = &events[..];
+    /// while !pending.is_empty() {
+    ///     let packed = port
+    ///         .send_batch_buf(pending, SystemTime::Infinite, &mut buf)
+    ///         .unwrap();
+    ///     pending = &pending[packed..];
+    /// }
+    /// # })
+    /// ```
+    fn send_batch_buf<T>(
+        &self,
+        items: &[T],
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<usize, BatchSendError>
+    where
+        T: Serialize;
+}
+
+/// Postcard batching extension trait for queuing port receiver
+pub trait QueuingPortBatchReceiverExt {
+    /// Receive a batch message using an a653rs [`QueuingPortReceiver`]
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Returns an iterator over the items of the batch.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_batch_buf(&[1u32, 2, 3], SystemTime::Infinite, &mut buf).unwrap();
+    /// let (batch, overflow) = port
+    ///     .recv_batch_buf::<u32>(SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// for item in batch {
+    ///     let item = item.unwrap();
+    /// }
+    /// # })
+    /// ```
+    fn recv_batch_buf<'a, T>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(Batch<'a, T>, QueueOverflow), QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>;
+}
+
+/// Iterator over the items of a received batch message
+///
+/// Yields a [`QueuingRecvBufError::Postcard`] with the bytes of an item
+/// if it fails to deserialize.
+/// If a length prefix is malformed, the remaining bytes are returned as an error
+/// and iteration ends.
+#[derive(Debug, Clone)]
+pub struct Batch<'a, T> {
+    rest: &'a [u8],
+    _t: PhantomData<T>,
+}
+
+impl<'a, T> Batch<'a, T> {
+    /// Iterate over the items of a batch message `msg`
+    pub fn new(msg: &'a [u8]) -> Self {
+        Self {
+            rest: msg,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Batch<'a, T>
+where
+    T: for<'b> Deserialize<'b>,
+{
+    type Item = Result<T, QueuingRecvBufError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let rest = core::mem::take(&mut self.rest);
+        let (len, data) = match postcard::take_from_bytes::<u32>(rest) {
+            Ok((len, data)) if len as usize <= data.len() => (len as usize, data),
+            Ok(_) => {
+                let e = postcard::Error::DeserializeUnexpectedEnd;
+                return Some(Err(QueuingRecvBufError::Postcard(e, rest)));
+            }
+            Err(e) => return Some(Err(QueuingRecvBufError::Postcard(e, rest))),
+        };
+        let (item, rest) = data.split_at(len);
+        self.rest = rest;
+        Some(postcard::from_bytes(item).map_err(|e| QueuingRecvBufError::Postcard(e, item)))
+    }
+}
+
+/// Number of bytes of the varint encoding of `n`
+fn varint_len(n: usize) -> usize {
+    let bits = usize::BITS - n.leading_zeros();
+    (bits as usize).div_ceil(7).max(1)
+}
+
+/// Serialize a length-prefixed `item` to the start of `buf`, returning the used length
+fn pack<T: Serialize>(item: &T, buf: &mut [u8]) -> Result<usize, postcard::Error> {
+    // Serialize behind the shortest possible prefix and
+    // move the item back if its prefix turns out longer
+    let data = buf
+        .get_mut(1..)
+        .ok_or(postcard::Error::SerializeBufferFull)?;
+    let len =
+        postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(item, SerSlice::new(data))?.len();
+    let prefix_len = varint_len(len);
+    if prefix_len + len > buf.len() {
+        return Err(postcard::Error::SerializeBufferFull);
+    }
+    let mut prefix = [0; 5];
+    let prefix = postcard::to_slice(&(len as u32), &mut prefix)?;
+    buf.copy_within(1..1 + len, prefix_len);
+    buf[..prefix_len].copy_from_slice(prefix);
+    Ok(prefix_len + len)
+}
+
+impl<Q: ApexQueuingPortP4Ext> QueuingPortBatchSenderExt for QueuingPortSender<Q> {
+    fn send_batch_buf<T>(
+        &self,
+        items: &[T],
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<usize, BatchSendError>
+    where
+        T: Serialize,
+    {
+        let limit = buf.len().min(self.size());
+        let buf = &mut buf[..limit];
+        let mut len = 0;
+        let mut packed = 0;
+        let mut error = None;
+        for item in items {
+            match pack(item, &mut buf[len..]) {
+                Ok(item_len) => {
+                    len += item_len;
+                    packed += 1;
+                }
+                Err(postcard::Error::SerializeBufferFull) if packed > 0 => break,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        if packed > 0 {
+            self.send(&buf[..len], timeout)
+                .map_err(|e| BatchSendError::new(0, e.into()))?;
+        }
+        match error {
+            Some(e) => Err(BatchSendError::new(packed, e.into())),
+            None => Ok(packed),
+        }
+    }
+}
+
+impl<Q: ApexQueuingPortP4Ext> QueuingPortBatchReceiverExt for QueuingPortReceiver<Q> {
+    fn recv_batch_buf<'a, T>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(Batch<'a, T>, QueueOverflow), QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        let (msg, overflow) = self.receive(buf, timeout)?;
+        Ok((Batch::new(msg), overflow))
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use super::varint_len;
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn batch_split() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    20,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    20,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 20];
+
+            // Every item takes 6 bytes including its prefix
+            let msgs = ["Test", "Test", "Test", "Test"].map(String::from);
+            let packed = src_port
+                .send_batch_buf(&msgs, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(3, packed);
+            let packed = src_port
+                .send_batch_buf(&msgs[packed..], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(1, packed);
+
+            let (batch, _) = dest_port
+                .recv_batch_buf::<String>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let batch: Vec<_> = batch.map(Result::unwrap).collect();
+            assert_eq!(&msgs[..3], batch.as_slice());
+            let (batch, _) = dest_port
+                .recv_batch_buf::<String>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(1, batch.count());
+
+            let too_large = [String::from("This string does not fit")];
+            assert!(src_port
+                .send_batch_buf(&too_large, SystemTime::Infinite, &mut buf)
+                .is_err());
+        })
+    }
+
+    /// Value failing to serialize
+    struct Unserializable;
+
+    impl serde::Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("unserializable"))
+        }
+    }
+
+    #[test]
+    fn batch_exact_fit() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    128,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 128];
+
+            // 127 bytes and a single byte prefix fill the message exactly
+            let msg = [String::from_iter(['x'; 126])];
+            let packed = src_port
+                .send_batch_buf(&msg, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(1, packed);
+        })
+    }
+
+    #[test]
+    fn batch_serialization_error() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    20,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    20,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 20];
+
+            let msgs = [Ok(1u8), Ok(2), Err(Unserializable)];
+            let err = src_port
+                .send_batch_buf(&msgs, SystemTime::Infinite, &mut buf)
+                .unwrap_err();
+            assert_eq!(2, err.sent);
+            assert!(matches!(err.error, SendError::Postcard(_)));
+
+            let (batch, _) = dest_port
+                .recv_batch_buf::<Result<u8, ()>>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(2, batch.count());
+        })
+    }
+
+    #[test]
+    fn batch_malformed() {
+        let mut batch = Batch::<u32>::new(&[1, 7, 5, 42]);
+        assert_eq!(7, batch.next().unwrap().unwrap());
+        assert!(matches!(
+            batch.next(),
+            Some(Err(QueuingRecvBufError::Postcard(_, [5, 42])))
+        ));
+        assert!(batch.next().is_none());
+    }
+
+    #[test]
+    fn varint_lengths() {
+        assert_eq!(1, varint_len(0));
+        assert_eq!(1, varint_len(127));
+        assert_eq!(2, varint_len(128));
+        assert_eq!(3, varint_len(1 << 14));
+    }
+}
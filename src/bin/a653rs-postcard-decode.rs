@@ -0,0 +1,211 @@
+//! Decode captured postcard messages using their schema
+//!
+//! The schema is taken from the port of an exported ICD or from a schema JSON file,
+//! the message bytes from a hex string, a file, a recording or stdin.
+
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use a653rs_postcard::prelude::*;
+
+const USAGE: &str = "\
+Usage: a653rs-postcard-decode (--icd <ICD.json> --port <NAME> | --schema <SCHEMA.json>)
+                              [--hex <BYTES> | --file <FILE> | --recording <FILE>] [--text]
+
+Decodes a postcard message read from stdin, unless given otherwise.
+
+Options:
+  --icd <ICD.json>        Take the schema of --port from an exported ICD
+  --port <NAME>           Port of the message, also selects the records of --recording
+  --schema <SCHEMA.json>  Take the schema from a JSON file
+  --hex <BYTES>           Hex string, e.g. `0a ff 01`, or a byte list, e.g. `[10, 255, 1]`
+  --file <FILE>           Raw message file
+  --recording <FILE>      Recording of port traffic, decoding every record
+  --text                  Print like Rust Debug output instead of JSON";
+
+#[derive(Default)]
+struct Args {
+    icd: Option<String>,
+    port: Option<String>,
+    schema: Option<String>,
+    hex: Option<String>,
+    file: Option<String>,
+    recording: Option<String>,
+    text: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let target = match arg.as_str() {
+                "--icd" => &mut parsed.icd,
+                "--port" => &mut parsed.port,
+                "--schema" => &mut parsed.schema,
+                "--hex" => &mut parsed.hex,
+                "--file" => &mut parsed.file,
+                "--recording" => &mut parsed.recording,
+                "--text" => {
+                    parsed.text = true;
+                    continue;
+                }
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            };
+            *target = Some(args.next().ok_or(format!("missing value of `{arg}`"))?);
+        }
+        Ok(parsed)
+    }
+
+    fn schema(&self) -> Result<OwnedNamedType, String> {
+        match (&self.icd, &self.port, &self.schema) {
+            (Some(icd), Some(port), None) => {
+                let json = std::fs::read_to_string(icd).map_err(|e| format!("{icd}: {e}"))?;
+                icd_schema(&json, port)
+                    .map_err(|e| format!("{icd}: {e}"))?
+                    .ok_or(format!("{icd}: no port called `{port}`"))
+            }
+            (None, _, Some(schema)) => {
+                let json = std::fs::read_to_string(schema).map_err(|e| format!("{schema}: {e}"))?;
+                serde_json::from_str(&json).map_err(|e| format!("{schema}: {e}"))
+            }
+            _ => Err("expected either `--icd` and `--port` or `--schema`".into()),
+        }
+    }
+
+    /// Messages to decode, with a description of their origin
+    fn messages(&self) -> Result<Vec<(String, Vec<u8>)>, String> {
+        match (&self.hex, &self.file, &self.recording) {
+            (Some(hex), None, None) => Ok(vec![(String::new(), parse_hex(hex)?)]),
+            (None, Some(file), None) => {
+                let bytes = std::fs::read(file).map_err(|e| format!("{file}: {e}"))?;
+                Ok(vec![(String::new(), bytes)])
+            }
+            (None, None, Some(recording)) => {
+                let file =
+                    std::fs::File::open(recording).map_err(|e| format!("{recording}: {e}"))?;
+                let records = read_recording(file).map_err(|e| format!("{recording}: {e}"))?;
+                Ok(records
+                    .into_iter()
+                    .filter(|r| self.port.as_ref().map_or(true, |p| *p == r.port))
+                    .map(|r| {
+                        let origin = format!("{} {} {:?}: ", r.timestamp, r.port, r.event);
+                        (origin, r.data)
+                    })
+                    .collect())
+            }
+            (None, None, None) => {
+                let mut bytes = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| format!("stdin: {e}"))?;
+                Ok(vec![(String::new(), bytes)])
+            }
+            _ => Err("expected at most one of `--hex`, `--file` and `--recording`".into()),
+        }
+    }
+}
+
+/// Parse hex digits or a list of decimal bytes, as printed by `{:?}`
+fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    let input = input.trim();
+    if let Some(list) = input.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        return list
+            .split(',')
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(|b| b.parse().map_err(|_| format!("invalid byte `{b}`")))
+            .collect();
+    }
+    let mut digits = Vec::new();
+    for token in input.split(|c: char| c.is_whitespace() || matches!(c, ':' | ',')) {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if let Some(c) = token.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex digit `{c}`"));
+        }
+        digits.extend_from_slice(token.as_bytes());
+    }
+    if digits.len() % 2 != 0 {
+        return Err("odd number of hex digits".into());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).unwrap_or_default();
+            u8::from_str_radix(pair, 16).map_err(|_| format!("invalid hex byte `{pair}`"))
+        })
+        .collect()
+}
+
+/// Print the bytes around the failing position, marking the failing byte
+fn print_error(err: &InspectError, bytes: &[u8]) {
+    let path = if err.path.is_empty() { "." } else { &err.path };
+    eprintln!("error at byte {} ({path}): {}", err.position, err.error);
+    let start = err.position.saturating_sub(8);
+    let end = (err.position + 8).min(bytes.len());
+    let mut line = String::new();
+    let mut marker = String::new();
+    for (i, b) in bytes.iter().enumerate().take(end).skip(start) {
+        line.push_str(&format!("{b:02x} "));
+        marker.push_str(if i == err.position { "^^ " } else { "   " });
+    }
+    if err.position >= bytes.len() {
+        marker.push_str("^^");
+    }
+    eprintln!("  {line}\n  {}", marker.trim_end());
+}
+
+fn run() -> Result<bool, String> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    let schema = args.schema()?;
+    let mut ok = true;
+    for (origin, bytes) in args.messages()? {
+        match inspect(&schema, &bytes) {
+            Ok((value, rest)) => {
+                if args.text {
+                    println!("{origin}{value:#?}");
+                } else {
+                    println!("{origin}{:#}", value.to_json());
+                }
+                if !rest.is_empty() {
+                    eprintln!("warning: {} trailing bytes", rest.len());
+                }
+            }
+            Err(err) => {
+                eprint!("{origin}");
+                print_error(&err, &bytes);
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_hex;
+
+    #[test]
+    fn decode_parse_hex() {
+        assert_eq!(Ok(vec![0x0a, 0x0b]), parse_hex("0x0a 0x0b"));
+        assert_eq!(Ok(vec![0x0a, 0x0b]), parse_hex("0x0a0b"));
+        assert_eq!(Ok(vec![1, 0xff]), parse_hex("01:FF"));
+        assert_eq!(Ok(vec![1, 2]), parse_hex("[1, 2]"));
+        assert!(parse_hex("0a0").is_err());
+        assert!(parse_hex("äb").is_err());
+        assert!(parse_hex("0aä").is_err());
+    }
+}
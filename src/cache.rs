@@ -0,0 +1,267 @@
+//! Last-known-good caching for sampling port destinations
+
+use a653rs::prelude::*;
+use serde::Deserialize;
+
+use crate::error::*;
+use crate::sampling::SamplingPortDestinationExt;
+
+/// Reason for a [`CachedSample`] being stale
+#[derive(Debug, Clone)]
+pub enum StaleReason {
+    /// The port returned a message with [`Validity::Invalid`]
+    Invalid,
+    /// Receiving from the port failed
+    Apex(a653rs::prelude::Error),
+    /// The received message failed to deserialize
+    Postcard(postcard::Error),
+    /// The received message exceeded decode limits
+    Limit(LimitError),
+    /// The received value failed validation
+    Validation(ValidationError),
+}
+
+/// Value returned by a [`CachedSamplingPortDestination`]
+#[derive(Debug, Clone)]
+pub struct CachedSample<'a, T> {
+    /// Last successfully decoded value
+    pub value: &'a T,
+    /// [`Validity`] of the message `value` was decoded from
+    pub validity: Validity,
+    /// System time at which `value` was received
+    pub timestamp: SystemTime,
+    /// Why the latest receive did not produce a new value, if it did not
+    pub stale: Option<StaleReason>,
+}
+
+impl<T> CachedSample<'_, T> {
+    /// Whether `value` was received by the latest receive
+    pub fn is_fresh(&self) -> bool {
+        self.stale.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry<T> {
+    value: T,
+    validity: Validity,
+    timestamp: SystemTime,
+}
+
+/// Sampling port destination falling back to the last known good value
+///
+/// Whenever the port returns [`Validity::Invalid`] data, an a653rs error
+/// or data which fails to deserialize, the last successfully decoded value
+/// is returned instead, flagged as stale.
+/// Values received with [`Validity::Invalid`] are only cached
+/// if no valid value was received yet.
+#[derive(Debug)]
+pub struct CachedSamplingPortDestination<T, S: ApexSamplingPortP4Ext + ApexTimeP4Ext> {
+    port: SamplingPortDestination<S>,
+    last: Option<Entry<T>>,
+}
+
+impl<T, S: ApexSamplingPortP4Ext + ApexTimeP4Ext> CachedSamplingPortDestination<T, S> {
+    /// Wrap `port` with an initially empty cache
+    pub fn new(port: SamplingPortDestination<S>) -> Self {
+        Self { port, last: None }
+    }
+
+    /// Receive a type, falling back to the last known good value
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Errors are only returned if no value was cached yet.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// let mut port = CachedSamplingPortDestination::<u32, _>::new(port);
+    /// let mut buf = [0; 500];
+    /// # src_port.send_type_buf(42u32, &mut buf).unwrap();
+    /// let sample = port.recv_type_buf(&mut buf).unwrap();
+    /// if !sample.is_fresh() {
+    ///     // Received data is outdated
+    /// }
+    /// # })
+    /// ```
+    pub fn recv_type_buf<'a>(
+        &mut self,
+        buf: &'a mut [u8],
+    ) -> Result<CachedSample<'_, T>, SamplingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        let stale = match self.port.recv_type_buf::<T>(buf) {
+            Ok((validity, value)) => self.update(validity, value),
+            Err(e) if self.last.is_none() => return Err(e),
+            Err(SamplingRecvBufError::Apex(e)) => Some(StaleReason::Apex(e)),
+            Err(SamplingRecvBufError::Postcard(e, _, _)) => Some(StaleReason::Postcard(e)),
+            Err(SamplingRecvBufError::Limit(e, _, _)) => Some(StaleReason::Limit(e)),
+            Err(SamplingRecvBufError::Validation(e, _, _)) => Some(StaleReason::Validation(e)),
+        };
+        Ok(self.sample(stale))
+    }
+
+    /// Receive a type, falling back to the last known good value
+    ///
+    /// Errors are only returned if no value was cached yet.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// let mut port = CachedSamplingPortDestination::<String, _>::new(port);
+    /// # src_port.send_type(String::default()).unwrap();
+    /// let sample = port.recv_type().unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn recv_type(&mut self) -> Result<CachedSample<'_, T>, SamplingRecvError>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        let stale = match self.port.recv_type::<T>() {
+            Ok((validity, value)) => self.update(validity, value),
+            Err(e) if self.last.is_none() => return Err(e),
+            Err(SamplingRecvError::Apex(e)) => Some(StaleReason::Apex(e)),
+            Err(SamplingRecvError::Postcard(e, _, _)) => Some(StaleReason::Postcard(e)),
+            Err(SamplingRecvError::Limit(e, _, _)) => Some(StaleReason::Limit(e)),
+            Err(SamplingRecvError::Validation(e, _, _)) => Some(StaleReason::Validation(e)),
+        };
+        Ok(self.sample(stale))
+    }
+
+    /// Last cached value, without receiving from the port
+    pub fn last(&self) -> Option<CachedSample<'_, T>> {
+        self.last.as_ref().map(|_| self.sample(None))
+    }
+
+    /// Drop the cached value
+    pub fn clear(&mut self) {
+        self.last = None;
+    }
+
+    /// Underlying sampling port
+    pub fn port(&self) -> &SamplingPortDestination<S> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying sampling port
+    pub fn into_inner(self) -> SamplingPortDestination<S> {
+        self.port
+    }
+
+    fn update(&mut self, validity: Validity, value: T) -> Option<StaleReason> {
+        if validity == Validity::Invalid
+            && matches!(&self.last, Some(e) if e.validity == Validity::Valid)
+        {
+            return Some(StaleReason::Invalid);
+        }
+        self.last = Some(Entry {
+            value,
+            validity,
+            timestamp: <S as ApexTimeP4Ext>::get_time(),
+        });
+        match validity {
+            Validity::Valid => None,
+            Validity::Invalid => Some(StaleReason::Invalid),
+        }
+    }
+
+    fn sample(&self, stale: Option<StaleReason>) -> CachedSample<'_, T> {
+        // Only called after a value was cached
+        let entry = self.last.as_ref().unwrap();
+        CachedSample {
+            value: &entry.value,
+            validity: entry.validity,
+            timestamp: entry.timestamp.clone(),
+            stale,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::Validity;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn cached_sampling_fallback() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+                .unwrap();
+            let dest_port = ctx
+                .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+                .unwrap();
+            let mut dest_port = CachedSamplingPortDestination::<u32, _>::new(dest_port);
+            let mut buf = [0; 500];
+
+            assert!(matches!(
+                dest_port.recv_type_buf(&mut buf),
+                Err(SamplingRecvBufError::Apex(_))
+            ));
+
+            MockHyp::advance(Duration::from_secs(1));
+            src_port.send_type_buf(42u32, &mut buf).unwrap();
+            let sample = dest_port.recv_type_buf(&mut buf).unwrap();
+            assert!(sample.is_fresh());
+            assert_eq!(42, *sample.value);
+
+            MockHyp::advance(Duration::from_secs(1));
+            src_port.send_type_buf(u64::MAX, &mut buf).unwrap();
+            let sample = dest_port.recv_type_buf(&mut buf).unwrap();
+            assert!(matches!(sample.stale, Some(StaleReason::Postcard(_))));
+            assert_eq!(42, *sample.value);
+            assert_eq!(SystemTime::Normal(Duration::from_secs(1)), sample.timestamp);
+
+            src_port.send_type_buf(7u32, &mut buf).unwrap();
+            MockHyp::set_validity(dest_port.port().id(), Validity::Invalid);
+            let sample = dest_port.recv_type_buf(&mut buf).unwrap();
+            assert!(matches!(sample.stale, Some(StaleReason::Invalid)));
+            assert_eq!(42, *sample.value);
+            assert_eq!(Validity::Valid, sample.validity);
+        })
+    }
+}
@@ -0,0 +1,400 @@
+//! CCSDS Space Packet encapsulation of postcard payloads
+//!
+//! Every message consists of a 6 byte Space Packet primary header (CCSDS 133.0-B)
+//! followed by the postcard-serialized value as packet data field.
+//! Packets are never segmented.
+
+use a653rs::prelude::*;
+use postcard::de_flavors::Slice as DeSlice;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+
+/// Length of the Space Packet primary header
+pub const PRIMARY_HEADER_LEN: usize = 6;
+
+/// Largest application process identifier
+pub const MAX_APID: u16 = 0x7FF;
+
+/// Largest packet sequence count, after which it wraps to zero
+pub const MAX_SEQUENCE_COUNT: u16 = 0x3FFF;
+
+/// Packet version number of Space Packets
+const VERSION: u8 = 0;
+
+/// Sequence flags of unsegmented packets
+const UNSEGMENTED: u16 = 0b11;
+
+/// Type of a Space Packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Telemetry,
+    Telecommand,
+}
+
+/// Space Packet primary header
+///
+/// The packet data length is derived from the payload when encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpacePacketHeader {
+    pub packet_type: PacketType,
+    /// Whether the packet data field starts with a secondary header
+    ///
+    /// The secondary header is part of the serialized value.
+    pub secondary_header: bool,
+    /// Application process identifier, at most [`MAX_APID`]
+    pub apid: u16,
+    /// Packet sequence count, at most [`MAX_SEQUENCE_COUNT`]
+    pub sequence_count: u16,
+}
+
+impl SpacePacketHeader {
+    /// Header of the first packet of `apid` without secondary header
+    pub fn new(packet_type: PacketType, apid: u16) -> Self {
+        Self {
+            packet_type,
+            secondary_header: false,
+            apid,
+            sequence_count: 0,
+        }
+    }
+
+    /// Increment the sequence count, wrapping after [`MAX_SEQUENCE_COUNT`]
+    pub fn increment(&mut self) {
+        self.sequence_count = self.sequence_count.wrapping_add(1) & MAX_SEQUENCE_COUNT;
+    }
+
+    /// Encode the header for a packet data field of `data_len` bytes
+    ///
+    /// Fails if the header fields are out of range or
+    /// the data field is empty or longer than 65536 bytes.
+    pub fn encode(&self, data_len: usize) -> Result<[u8; PRIMARY_HEADER_LEN], SpacePacketError> {
+        if self.apid > MAX_APID {
+            return Err(SpacePacketError::InvalidApid(self.apid));
+        }
+        if self.sequence_count > MAX_SEQUENCE_COUNT {
+            return Err(SpacePacketError::InvalidSequenceCount(self.sequence_count));
+        }
+        let data_length = data_len
+            .checked_sub(1)
+            .and_then(|len| u16::try_from(len).ok())
+            .ok_or(SpacePacketError::InvalidLength(data_len))?;
+
+        let packet_type = match self.packet_type {
+            PacketType::Telemetry => 0,
+            PacketType::Telecommand => 1,
+        };
+        let id = (u16::from(VERSION) << 13)
+            | (packet_type << 12)
+            | (u16::from(self.secondary_header) << 11)
+            | self.apid;
+        let sequence = (UNSEGMENTED << 14) | self.sequence_count;
+
+        let mut header = [0; PRIMARY_HEADER_LEN];
+        header[0..2].copy_from_slice(&id.to_be_bytes());
+        header[2..4].copy_from_slice(&sequence.to_be_bytes());
+        header[4..6].copy_from_slice(&data_length.to_be_bytes());
+        Ok(header)
+    }
+
+    /// Decode and validate the header of `packet`, returning the packet data field
+    ///
+    /// Fails if the packet is not an unsegmented version 1 Space Packet
+    /// or its length does not match the header.
+    pub fn decode(packet: &[u8]) -> Result<(Self, &[u8]), SpacePacketError> {
+        let (header, data) = packet
+            .split_first_chunk::<PRIMARY_HEADER_LEN>()
+            .ok_or(SpacePacketError::InvalidLength(packet.len()))?;
+        let id = u16::from_be_bytes([header[0], header[1]]);
+        let sequence = u16::from_be_bytes([header[2], header[3]]);
+        let data_length = u16::from_be_bytes([header[4], header[5]]);
+
+        let version = (id >> 13) as u8;
+        if version != VERSION {
+            return Err(SpacePacketError::InvalidVersion(version));
+        }
+        if sequence >> 14 != UNSEGMENTED {
+            return Err(SpacePacketError::Segmented);
+        }
+        if usize::from(data_length) + 1 != data.len() {
+            return Err(SpacePacketError::InvalidLength(packet.len()));
+        }
+
+        let packet_type = match (id >> 12) & 1 {
+            0 => PacketType::Telemetry,
+            _ => PacketType::Telecommand,
+        };
+        let header = Self {
+            packet_type,
+            secondary_header: (id >> 11) & 1 == 1,
+            apid: id & MAX_APID,
+            sequence_count: sequence & MAX_SEQUENCE_COUNT,
+        };
+        Ok((header, data))
+    }
+}
+
+/// Serialize `p` into a Space Packet with the given `header`
+///
+/// Requires a buffer `buf` for serialization.
+/// Returns the encoded packet.
+pub fn encode_space_packet_buf<'a, T>(
+    header: &SpacePacketHeader,
+    p: &T,
+    buf: &'a mut [u8],
+) -> Result<&'a mut [u8], SpacePacketSendError>
+where
+    T: Serialize + ?Sized,
+{
+    let data = buf
+        .get_mut(PRIMARY_HEADER_LEN..)
+        .ok_or(postcard::Error::SerializeBufferFull)?;
+    let data_len =
+        postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(p, SerSlice::new(data))?.len();
+    let encoded = header.encode(data_len)?;
+    buf[..PRIMARY_HEADER_LEN].copy_from_slice(&encoded);
+    Ok(&mut buf[..PRIMARY_HEADER_LEN + data_len])
+}
+
+/// Validate the Space Packet `packet` and deserialize its data field
+///
+/// Returns the primary header together with the value.
+pub fn decode_space_packet<'a, T>(
+    packet: &'a [u8],
+) -> Result<(SpacePacketHeader, T), SpacePacketRecvBufError<'a>>
+where
+    T: Deserialize<'a>,
+{
+    let (header, data) = SpacePacketHeader::decode(packet)
+        .map_err(|e| SpacePacketRecvBufError::SpacePacket(e, packet))?;
+    let mut de = postcard::Deserializer::from_flavor(DeSlice::new(data));
+    let t = T::deserialize(&mut de).map_err(|e| SpacePacketRecvBufError::Postcard(e, data))?;
+    Ok((header, t))
+}
+
+/// Space Packet extension trait for queuing port sender
+pub trait QueuingPortSpacePacketSenderExt {
+    /// Send a type as Space Packet using an a653rs [`QueuingPortSender`]
+    ///
+    /// Requires a buffer `buf` for serialization.
+    /// The sequence count of `header` is incremented after the packet was sent.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let mut header = SpacePacketHeader::new(PacketType::Telemetry, 0x42);
+    /// let mut buf = [0; 500];
+    /// port.send_space_packet_buf(&mut header, 21.5f32, SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// assert_eq!(1, header.sequence_count);
+    /// # })
+    /// ```
+    fn send_space_packet_buf<T>(
+        &self,
+        header: &mut SpacePacketHeader,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SpacePacketSendError>
+    where
+        T: Serialize;
+}
+
+/// Space Packet extension trait for queuing port receiver
+pub trait QueuingPortSpacePacketReceiverExt {
+    /// Receive a type from a Space Packet using an a653rs [`QueuingPortReceiver`]
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Returns the primary header together with the value.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # let mut header = SpacePacketHeader::new(PacketType::Telecommand, 0x10);
+    /// # src_port.send_space_packet_buf(&mut header, "Reset", SystemTime::Infinite, &mut buf).unwrap();
+    /// let (header, cmd, _) = port
+    ///     .recv_space_packet_buf::<&str>(SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// assert_eq!(0x10, header.apid);
+    /// # })
+    /// ```
+    fn recv_space_packet_buf<'a, T>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(SpacePacketHeader, T, QueueOverflow), SpacePacketRecvBufError<'a>>
+    where
+        T: Deserialize<'a>;
+}
+
+impl<Q: ApexQueuingPortP4Ext> QueuingPortSpacePacketSenderExt for QueuingPortSender<Q> {
+    fn send_space_packet_buf<T>(
+        &self,
+        header: &mut SpacePacketHeader,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SpacePacketSendError>
+    where
+        T: Serialize,
+    {
+        let packet = encode_space_packet_buf(header, &p, buf)?;
+        self.send(packet, timeout)?;
+        header.increment();
+        Ok(())
+    }
+}
+
+impl<Q: ApexQueuingPortP4Ext> QueuingPortSpacePacketReceiverExt for QueuingPortReceiver<Q> {
+    fn recv_space_packet_buf<'a, T>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(SpacePacketHeader, T, QueueOverflow), SpacePacketRecvBufError<'a>>
+    where
+        T: Deserialize<'a>,
+    {
+        let (packet, overflow) = self.receive(buf, timeout)?;
+        let (header, t) = decode_space_packet(packet)?;
+        Ok((header, t, overflow))
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn space_packet_header() {
+        let mut header = SpacePacketHeader {
+            packet_type: PacketType::Telecommand,
+            secondary_header: true,
+            apid: 0x123,
+            sequence_count: MAX_SEQUENCE_COUNT,
+        };
+        let encoded = header.encode(2).unwrap();
+        assert_eq!([0x19, 0x23, 0xFF, 0xFF, 0x00, 0x01], encoded);
+        let mut packet = [0; 8];
+        packet[..6].copy_from_slice(&encoded);
+        assert_eq!(
+            (header, &[0, 0][..]),
+            SpacePacketHeader::decode(&packet).unwrap()
+        );
+
+        header.increment();
+        assert_eq!(0, header.sequence_count);
+        assert!(matches!(
+            header.encode(0),
+            Err(SpacePacketError::InvalidLength(0))
+        ));
+        header.apid = 0x800;
+        assert!(matches!(
+            header.encode(1),
+            Err(SpacePacketError::InvalidApid(0x800))
+        ));
+
+        assert!(matches!(
+            SpacePacketHeader::decode(&packet[..7]),
+            Err(SpacePacketError::InvalidLength(7))
+        ));
+        packet[2] = 0x7F;
+        assert!(matches!(
+            SpacePacketHeader::decode(&packet),
+            Err(SpacePacketError::Segmented)
+        ));
+        packet[0] = 0x39;
+        assert!(matches!(
+            SpacePacketHeader::decode(&packet),
+            Err(SpacePacketError::InvalidVersion(1))
+        ));
+    }
+
+    #[test]
+    fn space_packet_queuing() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 100];
+
+            let mut header = SpacePacketHeader::new(PacketType::Telemetry, 0x42);
+            for value in [7u32, 8] {
+                src_port
+                    .send_space_packet_buf(&mut header, value, SystemTime::Infinite, &mut buf)
+                    .unwrap();
+            }
+            // Unit types have an empty data field
+            assert!(matches!(
+                src_port.send_space_packet_buf(&mut header, (), SystemTime::Infinite, &mut buf),
+                Err(SpacePacketSendError::SpacePacket(
+                    SpacePacketError::InvalidLength(0)
+                ))
+            ));
+            assert_eq!(2, header.sequence_count);
+
+            for (count, value) in [(0, 7), (1, 8)] {
+                let (header, rec, _) = dest_port
+                    .recv_space_packet_buf::<u32>(SystemTime::Infinite, &mut buf)
+                    .unwrap();
+                assert_eq!(0x42, header.apid);
+                assert_eq!(PacketType::Telemetry, header.packet_type);
+                assert_eq!(count, header.sequence_count);
+                assert_eq!(value, rec);
+            }
+        })
+    }
+}
@@ -0,0 +1,555 @@
+//! COBS-framed byte streams over queuing ports
+//!
+//! Values are serialized using postcard's COBS flavor, which terminates every frame
+//! with a zero byte.
+//! The resulting byte stream is split into messages of at most the port size,
+//! so that values may be larger than a single message,
+//! and a single message may contain several small values.
+//! Relaying the stream to a serial link keeps the framing identical.
+
+use a653rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+
+/// COBS frame delimiter
+const DELIMITER: u8 = 0;
+
+/// Queuing port sender writing a COBS-framed byte stream
+#[derive(Debug)]
+pub struct CobsQueuingPortSender<Q: ApexQueuingPortP4Ext> {
+    port: QueuingPortSender<Q>,
+}
+
+impl<Q: ApexQueuingPortP4Ext> CobsQueuingPortSender<Q> {
+    /// Write a byte stream to `port`
+    pub fn new(port: QueuingPortSender<Q>) -> Self {
+        Self { port }
+    }
+
+    /// Send a type as COBS frame
+    ///
+    /// Requires a buffer `buf` for serialization.
+    /// Frames larger than the port size are split into several messages,
+    /// each waiting up to `timeout`.
+    /// If sending any of them fails, the incomplete frame is not terminated,
+    /// so the receiver fails to deserialize it together with the next frame.
+    ///
+    /// Use [`send_types_buf`](Self::send_types_buf) for sending several small values
+    /// in a single message.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let port = CobsQueuingPortSender::new(port);
+    /// let mut buf = [0; 500];
+    /// port.send_type_buf("Larger than a single message", SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    pub fn send_type_buf<T>(
+        &self,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError>
+    where
+        T: Serialize,
+    {
+        self.send_types_buf([p], timeout, buf)
+    }
+
+    /// Send several types as consecutive COBS frames
+    ///
+    /// Frames are packed into messages of the port size,
+    /// so that small frames share a single message.
+    /// Requires a buffer `buf` for serialization, which must fit
+    /// the largest frame and the bytes of the previous frames not sent yet,
+    /// which are less than the port size.
+    /// Every message waits up to `timeout`.
+    /// If sending or serializing fails, the preceding frames which were not sent yet are lost.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let port = CobsQueuingPortSender::new(port);
+    /// let mut buf = [0; 32];
+    /// // All frames are sent in a single message
+    /// port.send_types_buf([1u8, 2, 3, 4], SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    pub fn send_types_buf<T, I>(
+        &self,
+        values: I,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Serialize,
+    {
+        let size = self.port.size();
+        let mut len = 0;
+        for p in values {
+            len += postcard::to_slice_cobs(&p, &mut buf[len..])?.len();
+            // Send complete messages, keeping the rest for the next frames
+            let full = len - len % size;
+            for msg in buf[..full].chunks(size) {
+                self.port.send(msg, timeout.clone())?;
+            }
+            buf.copy_within(full..len, 0);
+            len -= full;
+        }
+        if len > 0 {
+            self.port.send(&buf[..len], timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortSender<Q> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying queuing port
+    pub fn into_inner(self) -> QueuingPortSender<Q> {
+        self.port
+    }
+}
+
+/// Queuing port receiver reading a COBS-framed byte stream
+///
+/// Bytes of a received message which belong to the next frame are kept
+/// for the next call.
+/// `N` is the size of the internal message buffer.
+/// It must be at least the size of the port.
+#[derive(Debug)]
+pub struct CobsQueuingPortReceiver<const N: usize, Q: ApexQueuingPortP4Ext> {
+    port: QueuingPortReceiver<Q>,
+    pending: [u8; N],
+    start: usize,
+    end: usize,
+    /// Bytes of an incomplete frame at the start of the caller's buffer
+    partial: usize,
+    resync: bool,
+}
+
+/// Failure of reading a frame, not borrowing the buffer
+enum FrameError {
+    Apex(Error),
+    /// The frame did not fit into the buffer, whose first bytes were filled
+    TooLarge,
+}
+
+impl<const N: usize, Q: ApexQueuingPortP4Ext> CobsQueuingPortReceiver<N, Q> {
+    /// Read a byte stream from `port`
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the port size exceeds `N`.
+    pub fn new(port: QueuingPortReceiver<Q>) -> Result<Self, Error> {
+        if port.size() > N {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(Self {
+            port,
+            pending: [0; N],
+            start: 0,
+            end: 0,
+            partial: 0,
+            resync: false,
+        })
+    }
+
+    /// Receive a type from the next COBS frame
+    ///
+    /// Requires a buffer `buf` for assembling and deserializing the frame.
+    /// Every message which needs to be received waits up to `timeout`.
+    /// Returns whether the queue overflowed while receiving any of the messages.
+    ///
+    /// If no message is available before `timeout` in the middle of a frame,
+    /// the bytes received so far are kept at the start of `buf`
+    /// and the next call resumes the frame.
+    /// Pass the same buffer unchanged for resuming,
+    /// the frame is discarded if the buffer is too small for the kept bytes.
+    /// If receiving fails with any other error in the middle of a frame,
+    /// the remainder of the frame is skipped by the next call.
+    ///
+    /// Frames larger than `buf` are discarded and reported as
+    /// [`postcard::Error::DeserializeUnexpectedEnd`] with the beginning of the frame.
+    /// Frames failing to deserialize are returned as they were after decoding in place.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # CobsQueuingPortSender::new(src_port)
+    /// #     .send_type_buf("Larger than a single message", SystemTime::Infinite, &mut buf)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut port = CobsQueuingPortReceiver::<16, _>::new(port).unwrap();
+    /// let (msg, _) = port
+    ///     .recv_type_buf::<String>(SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// assert_eq!("Larger than a single message", msg);
+    /// # })
+    /// ```
+    pub fn recv_type_buf<'a, T>(
+        &mut self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        let mut overflow = false;
+        match self.read_frame(timeout, buf, &mut overflow) {
+            Ok(len) => match postcard::from_bytes_cobs::<T>(&mut buf[..len]) {
+                Ok(t) => Ok((t, overflow)),
+                Err(e) => Err(QueuingRecvBufError::Postcard(e, &buf[..len])),
+            },
+            Err(FrameError::Apex(e)) => Err(e.into()),
+            Err(FrameError::TooLarge) => {
+                let e = postcard::Error::DeserializeUnexpectedEnd;
+                Err(QueuingRecvBufError::Postcard(e, buf))
+            }
+        }
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortReceiver<Q> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying queuing port
+    ///
+    /// Bytes of incomplete frames are discarded.
+    pub fn into_inner(self) -> QueuingPortReceiver<Q> {
+        self.port
+    }
+
+    /// Assemble the next frame including its delimiter at the start of `buf`
+    fn read_frame(
+        &mut self,
+        timeout: SystemTime,
+        buf: &mut [u8],
+        overflow: &mut QueueOverflow,
+    ) -> Result<usize, FrameError> {
+        let mut len = core::mem::take(&mut self.partial);
+        if len > buf.len() {
+            // The kept bytes do not fit, so the frame is lost
+            len = 0;
+            self.resync = true;
+        }
+        loop {
+            if self.start == self.end {
+                let (msg, o) = match self.port.receive(&mut self.pending, timeout.clone()) {
+                    Ok(received) => received,
+                    Err(e @ (Error::NotAvailable | Error::TimedOut)) => {
+                        // Resume the current frame with the next call
+                        self.partial = len;
+                        return Err(FrameError::Apex(e));
+                    }
+                    Err(e) => {
+                        // Bytes of the current frame are lost
+                        self.resync |= len > 0;
+                        return Err(FrameError::Apex(e));
+                    }
+                };
+                *overflow |= o;
+                self.start = 0;
+                self.end = msg.len();
+            }
+
+            let data = &self.pending[self.start..self.end];
+            let delimiter = data.iter().position(|b| *b == DELIMITER);
+            if self.resync {
+                // Skip the remainder of a discarded frame
+                match delimiter {
+                    Some(i) => {
+                        self.start += i + 1;
+                        self.resync = false;
+                    }
+                    None => self.start = self.end,
+                }
+                continue;
+            }
+
+            let chunk = match delimiter {
+                Some(i) => &data[..=i],
+                None => data,
+            };
+            let Some(dest) = buf.get_mut(len..len + chunk.len()) else {
+                let fitting = buf.len() - len;
+                buf[len..].copy_from_slice(&chunk[..fitting]);
+                self.start += chunk.len();
+                self.resync = delimiter.is_none();
+                return Err(FrameError::TooLarge);
+            };
+            dest.copy_from_slice(chunk);
+            len += chunk.len();
+            self.start += chunk.len();
+            if delimiter.is_some() {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+    use std::string::String;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Error, Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn cobs_stream() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut dest_port = CobsQueuingPortReceiver::<8, _>::new(dest_port).unwrap();
+            let mut buf = [0; 100];
+
+            // Several small frames in a single message
+            let mut frames = [0; 8];
+            let len = postcard::to_slice_cobs(&1u8, &mut frames).unwrap().len();
+            let len = len
+                + postcard::to_slice_cobs(&2u8, &mut frames[len..])
+                    .unwrap()
+                    .len();
+            src_port.send(&frames[..len], SystemTime::Infinite).unwrap();
+            let src_port = CobsQueuingPortSender::new(src_port);
+            src_port
+                .send_type_buf("Spans several messages", SystemTime::Infinite, &mut buf)
+                .unwrap();
+
+            for expected in [1, 2] {
+                let (rec, _) = dest_port
+                    .recv_type_buf::<u8>(SystemTime::Infinite, &mut buf)
+                    .unwrap();
+                assert_eq!(expected, rec);
+            }
+            let (rec, _) = dest_port
+                .recv_type_buf::<String>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!("Spans several messages", rec);
+        })
+    }
+
+    #[test]
+    fn cobs_resync() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let other_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("Other").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            assert!(CobsQueuingPortReceiver::<4, _>::new(other_port).is_err());
+            let mut dest_port = CobsQueuingPortReceiver::<8, _>::new(dest_port).unwrap();
+            let src_port = CobsQueuingPortSender::new(src_port);
+            let mut buf = [0; 100];
+
+            src_port
+                .send_type_buf("Too large", SystemTime::Infinite, &mut buf)
+                .unwrap();
+            src_port
+                .send_type_buf(42u32, SystemTime::Infinite, &mut buf)
+                .unwrap();
+
+            let mut small = [0; 4];
+            assert!(matches!(
+                dest_port.recv_type_buf::<String>(SystemTime::Infinite, &mut small),
+                Err(QueuingRecvBufError::Postcard(
+                    postcard::Error::DeserializeUnexpectedEnd,
+                    _
+                ))
+            ));
+            let (rec, _) = dest_port
+                .recv_type_buf::<u32>(SystemTime::Infinite, &mut small)
+                .unwrap();
+            assert_eq!(42, rec);
+        })
+    }
+
+    #[test]
+    fn cobs_timeout_mid_frame() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut dest_port = CobsQueuingPortReceiver::<8, _>::new(dest_port).unwrap();
+            let mut buf = [0; 100];
+
+            let mut frame = [0; 100];
+            let frame = postcard::to_slice_cobs("Spans several messages", &mut frame).unwrap();
+            let (head, tail) = frame.split_at(8);
+            src_port.send(head, SystemTime::Infinite).unwrap();
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            assert!(matches!(
+                dest_port.recv_type_buf::<String>(timeout, &mut buf),
+                Err(QueuingRecvBufError::Apex(Error::NotAvailable))
+            ));
+
+            for msg in tail.chunks(8) {
+                src_port.send(msg, SystemTime::Infinite).unwrap();
+            }
+            let src_port = CobsQueuingPortSender::new(src_port);
+            src_port
+                .send_type_buf(42u32, SystemTime::Infinite, &mut [0; 8])
+                .unwrap();
+            let (rec, _) = dest_port
+                .recv_type_buf::<String>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!("Spans several messages", rec);
+            let (rec, _) = dest_port
+                .recv_type_buf::<u32>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(42, rec);
+        })
+    }
+
+    #[test]
+    fn cobs_pack() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    8,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let src_port = CobsQueuingPortSender::new(src_port);
+            let mut buf = [0; 16];
+
+            // Frames of three bytes each, packed into messages of eight bytes
+            src_port
+                .send_types_buf([1u8, 2, 3], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            src_port
+                .send_types_buf([4u8, 5, 6, 7, 8], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut msg = [0; 8];
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            for expected in [8, 1, 8, 7] {
+                let (rec, _) = dest_port.receive(&mut msg, timeout.clone()).unwrap();
+                assert_eq!(expected, rec.len());
+            }
+            assert!(dest_port.receive(&mut msg, timeout.clone()).is_err());
+
+            let mut dest_port = CobsQueuingPortReceiver::<8, _>::new(dest_port).unwrap();
+            src_port
+                .send_types_buf([1u8, 2, 3], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            for expected in [1, 2, 3] {
+                let (rec, _) = dest_port
+                    .recv_type_buf::<u8>(timeout.clone(), &mut buf)
+                    .unwrap();
+                assert_eq!(expected, rec);
+            }
+        })
+    }
+}
@@ -0,0 +1,186 @@
+//! Pluggable message codecs
+//!
+//! All extension traits use [`Postcard`] by default.
+//! Their `_with` variants accept any codec implementing [`Encode`] or [`Decode`]
+//! for the sent or received type, while keeping the same error structure.
+//! Errors of other codecs are reported in the `Postcard` variants of the error types,
+//! unless the codec classifies them as violated limits using [`Codec::failure`].
+
+use core::fmt::Debug;
+
+use postcard::de_flavors::Slice as DeSlice;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::error::DecodeFailure;
+
+/// Message codec
+pub trait Codec {
+    /// Error of encoding or decoding a message
+    type Error: Debug;
+
+    /// Classify a decode error, selecting the variant of the receive errors reporting it
+    ///
+    /// All errors are reported in the `Postcard` variants by default.
+    fn failure(e: Self::Error) -> DecodeFailure<Self::Error> {
+        DecodeFailure::Codec(e)
+    }
+}
+
+/// Codec able to encode values of type `T`
+pub trait Encode<T: ?Sized>: Codec {
+    /// Encode `value` into the start of `buf`, returning the encoded message
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error>;
+}
+
+/// Codec able to decode values of type `T`
+pub trait Decode<T>: Codec {
+    /// Decode a value from the message `msg`
+    fn decode(&self, msg: &[u8]) -> Result<T, Self::Error>;
+
+    /// Decode a value from the message `msg` into an existing `place`
+    ///
+    /// Implementations may overwrite `place` while decoding,
+    /// so on failure its contents are unspecified, but valid.
+    /// The default implementation decodes into a temporary before replacing `place`,
+    /// codecs able to decode in place should override it.
+    fn decode_in_place(&self, msg: &[u8], place: &mut T) -> Result<(), Self::Error> {
+        *place = self.decode(msg)?;
+        Ok(())
+    }
+}
+
+/// Default codec using postcard's wire format
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+impl Codec for Postcard {
+    type Error = postcard::Error;
+}
+
+impl<T: Serialize + ?Sized> Encode<T> for Postcard {
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], postcard::Error> {
+        postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(value, SerSlice::new(buf))
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> Decode<T> for Postcard {
+    fn decode(&self, msg: &[u8]) -> Result<T, postcard::Error> {
+        let mut deserializer = postcard::Deserializer::from_flavor(DeSlice::new(msg));
+        T::deserialize(&mut deserializer)
+    }
+
+    /// Deserialize in place using [`Deserialize::deserialize_in_place`]
+    ///
+    /// Sequences, strings, arrays and options reuse the existing value.
+    /// Derived structs only do so if serde's `deserialize_in_place` feature is enabled,
+    /// otherwise they are deserialized into a temporary.
+    fn decode_in_place(&self, msg: &[u8], place: &mut T) -> Result<(), postcard::Error> {
+        let mut deserializer = postcard::Deserializer::from_flavor(DeSlice::new(msg));
+        T::deserialize_in_place(&mut deserializer, place)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    /// Fixed-layout codec of a legacy interface
+    struct BigEndian;
+
+    impl Codec for BigEndian {
+        type Error = ();
+    }
+
+    impl Encode<u32> for BigEndian {
+        fn encode<'a>(&self, value: &u32, buf: &'a mut [u8]) -> Result<&'a mut [u8], ()> {
+            let buf = buf.get_mut(..4).ok_or(())?;
+            buf.copy_from_slice(&value.to_be_bytes());
+            Ok(buf)
+        }
+    }
+
+    impl Decode<u32> for BigEndian {
+        fn decode(&self, msg: &[u8]) -> Result<u32, ()> {
+            Ok(u32::from_be_bytes(msg.try_into().map_err(|_| ())?))
+        }
+    }
+
+    #[test]
+    fn custom_codec() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("Q").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("Q").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let sampling_src = ctx
+                .create_sampling_port_source(Name::from_str("S").unwrap(), 100)
+                .unwrap();
+            let sampling_dest = ctx
+                .create_sampling_port_destination(Name::from_str("S").unwrap(), 100, Duration::ZERO)
+                .unwrap();
+            let mut buf = [0; 100];
+
+            src_port
+                .send_type_buf_with(&BigEndian, 0x0102_0304, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let (rec, _) = dest_port
+                .recv_type_buf::<[u8; 4]>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!([1, 2, 3, 4], rec);
+
+            src_port
+                .send_type_buf(1u8, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, u32>(&BigEndian, SystemTime::Infinite, &mut buf),
+                Err(QueuingRecvBufError::Postcard((), [1]))
+            ));
+
+            sampling_src
+                .send_type_buf_with(&BigEndian, 42, &mut buf)
+                .unwrap();
+            let mut rec = 0;
+            sampling_dest
+                .recv_into_with(&BigEndian, &mut rec, &mut buf)
+                .unwrap();
+            assert_eq!(42, rec);
+        })
+    }
+
+    #[test]
+    fn postcard_in_place() {
+        let mut place = [0u8; 4];
+        Postcard.decode_in_place(&[1, 2, 3, 4], &mut place).unwrap();
+        assert_eq!([1, 2, 3, 4], place);
+
+        // Elements are deserialized directly into the existing array
+        assert!(Postcard.decode_in_place(&[5, 6], &mut place).is_err());
+        assert_eq!([5, 6, 3, 4], place);
+    }
+}
@@ -0,0 +1,689 @@
+//! Checking typed port declarations against an ARINC 653 module configuration
+//!
+//! [`ModuleConfig`] parses the partitions, ports and channels of an ARINC 653 configuration XML.
+//! [`ModuleConfig::check`] compares them with the [`Icd`]s of the partitions' typed port
+//! declarations, reporting every [`Mismatch`] before the partitions are integrated on the target.
+
+extern crate std;
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use a653rs::prelude::MessageRange;
+use roxmltree::{Document, Node};
+
+use crate::error::ConfigError;
+use crate::icd::{Icd, PortKind};
+
+/// Port of a partition in the module configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortConfig {
+    /// Port name
+    pub name: String,
+    /// Kind and direction of the port
+    pub kind: PortKind,
+    /// Configured `MaxMessageSize`
+    pub max_message_size: usize,
+    /// Configured `MaxNbMessages` of a queuing port
+    pub max_nb_messages: Option<MessageRange>,
+    /// Configured `RefreshRateSeconds` of a sampling port in milliseconds, if present
+    pub refresh_ms: Option<u64>,
+}
+
+/// Partition in the module configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionConfig {
+    /// Partition name
+    pub name: String,
+    /// Partition identifier, if configured
+    pub identifier: Option<String>,
+    /// Ports of the partition
+    pub ports: Vec<PortConfig>,
+}
+
+impl PartitionConfig {
+    /// Port called `name`
+    pub fn port(&self, name: &str) -> Option<&PortConfig> {
+        self.ports.iter().find(|p| p.name == name)
+    }
+}
+
+/// Port of a partition connected by a channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortRef {
+    /// Partition name
+    pub partition: String,
+    /// Port name
+    pub port: String,
+}
+
+/// Channel connecting a source port to destination ports
+///
+/// Ports of pseudo partitions are not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// Channel name, or identifier if no name is configured
+    pub name: String,
+    /// Source port, if it belongs to a standard partition
+    pub source: Option<PortRef>,
+    /// Destination ports belonging to standard partitions
+    pub destinations: Vec<PortRef>,
+}
+
+/// Inconsistency between the module configuration and typed port declarations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// A described partition is not configured
+    UnknownPartition { partition: String },
+    /// A declared port is not configured for its partition
+    UnknownPort { partition: String, port: String },
+    /// A port is configured as a different kind or direction than declared
+    Kind {
+        partition: String,
+        port: String,
+        configured: PortKind,
+        declared: PortKind,
+    },
+    /// The declared message size differs from the configured one,
+    /// which fails creating the port with `Error::InvalidConfig`
+    MessageSize {
+        partition: String,
+        port: String,
+        configured: usize,
+        max_size: usize,
+    },
+    /// A queuing port is configured with a different depth than declared
+    QueueDepth {
+        partition: String,
+        port: String,
+        configured: MessageRange,
+        declared: MessageRange,
+    },
+    /// A sampling port is configured with a different refresh period than declared,
+    /// both in milliseconds
+    Refresh {
+        partition: String,
+        port: String,
+        configured: u64,
+        declared: u64,
+    },
+    /// The ends of a channel use different message types
+    MessageType {
+        channel: String,
+        source: PortRef,
+        destination: PortRef,
+    },
+    /// A channel connects a destination port as source or a source port as destination
+    Direction {
+        channel: String,
+        port: PortRef,
+        configured: PortKind,
+    },
+    /// A channel connects a queuing port with a sampling port
+    MixedPorts {
+        channel: String,
+        source: PortRef,
+        destination: PortRef,
+    },
+}
+
+/// Partitions and channels of an ARINC 653 module configuration
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, MaxSize, Schema)]
+/// struct Status {
+///     mode: u8,
+///     uptime: u32,
+/// }
+///
+/// #[derive(PartitionPorts, PartitionIcd)]
+/// struct Ports<H: ApexSamplingPortP4Ext> {
+///     #[sampling(name = "STATUS")]
+///     status: TypedSamplingPortSource<Status, H>,
+/// }
+///
+/// let config = ModuleConfig::parse(
+///     r#"<ARINC_653_Module ModuleName="Module">
+///         <Partition PartitionIdentifier="1" PartitionName="Controller">
+///             <Sampling_Port Name="STATUS" Direction="SOURCE" MaxMessageSize="4"/>
+///         </Partition>
+///     </ARINC_653_Module>"#,
+/// )
+/// .unwrap();
+/// let mismatches = config.check(&[("Controller", Ports::<Hypervisor>::icd())]);
+/// assert!(matches!(
+///     &mismatches[..],
+///     [Mismatch::MessageSize { configured: 4, max_size: 6, .. }]
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleConfig {
+    /// Configured partitions
+    pub partitions: Vec<PartitionConfig>,
+    /// Configured channels
+    pub channels: Vec<ChannelConfig>,
+}
+
+impl ModuleConfig {
+    /// Parse the partitions and channels of an ARINC 653 configuration XML
+    pub fn parse(xml: &str) -> Result<Self, ConfigError> {
+        let doc = Document::parse(xml)?;
+        let root = doc.root_element();
+
+        let partitions = root
+            .descendants()
+            .filter(|n| n.has_tag_name("Partition"))
+            .map(parse_partition)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut channels = Vec::new();
+        for channel in root.descendants().filter(|n| n.has_tag_name("Channel")) {
+            let name = match channel.attribute("ChannelName") {
+                Some(name) => name,
+                None => attribute(channel, "ChannelIdentifier")?,
+            };
+            let mut source = None;
+            let mut destinations = Vec::new();
+            for end in channel.children().filter(Node::is_element) {
+                let mut ports = end
+                    .children()
+                    .filter(|n| n.has_tag_name("Standard_Partition"))
+                    .map(|n| parse_port_ref(n, &partitions));
+                match end.tag_name().name() {
+                    "Source" => source = ports.next().transpose()?,
+                    "Destination" => destinations.extend(ports.collect::<Result<Vec<_>, _>>()?),
+                    _ => {}
+                }
+            }
+            channels.push(ChannelConfig {
+                name: name.to_string(),
+                source,
+                destinations,
+            });
+        }
+
+        Ok(Self {
+            partitions,
+            channels,
+        })
+    }
+
+    /// Partition called `name`
+    pub fn partition(&self, name: &str) -> Option<&PartitionConfig> {
+        self.partitions.iter().find(|p| p.name == name)
+    }
+
+    /// Check the ICDs of named partitions against the configuration
+    ///
+    /// Ports of partitions which are not described are not checked,
+    /// neither are configured ports which are not declared.
+    /// The directions and kinds of the ports connected by every channel are checked as well.
+    /// Returns every mismatch found.
+    pub fn check(&self, interfaces: &[(&str, Icd)]) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        for (partition, icd) in interfaces {
+            let Some(config) = self.partition(partition) else {
+                mismatches.push(Mismatch::UnknownPartition {
+                    partition: partition.to_string(),
+                });
+                continue;
+            };
+            for declared in &icd.ports {
+                let partition = partition.to_string();
+                let port = declared.name.to_string();
+                let Some(configured) = config.port(declared.name) else {
+                    mismatches.push(Mismatch::UnknownPort { partition, port });
+                    continue;
+                };
+                if configured.kind != declared.kind {
+                    mismatches.push(Mismatch::Kind {
+                        partition: partition.clone(),
+                        port: port.clone(),
+                        configured: configured.kind,
+                        declared: declared.kind,
+                    });
+                }
+                if declared.max_size != configured.max_message_size {
+                    mismatches.push(Mismatch::MessageSize {
+                        partition: partition.clone(),
+                        port: port.clone(),
+                        configured: configured.max_message_size,
+                        max_size: declared.max_size,
+                    });
+                }
+                if let (Some(configured), Some(declared)) =
+                    (configured.max_nb_messages, declared.depth)
+                {
+                    if configured != declared {
+                        mismatches.push(Mismatch::QueueDepth {
+                            partition: partition.clone(),
+                            port: port.clone(),
+                            configured,
+                            declared,
+                        });
+                    }
+                }
+                if let (Some(configured), Some(declared)) =
+                    (configured.refresh_ms, declared.refresh_ms)
+                {
+                    if configured != declared {
+                        mismatches.push(Mismatch::Refresh {
+                            partition,
+                            port,
+                            configured,
+                            declared,
+                        });
+                    }
+                }
+            }
+        }
+
+        let schema = |port: &PortRef| {
+            let (_, icd) = interfaces.iter().find(|(p, _)| *p == port.partition)?;
+            Some(icd.port(&port.port)?.schema)
+        };
+        let kind = |port: &PortRef| Some(self.partition(&port.partition)?.port(&port.port)?.kind);
+        for channel in &self.channels {
+            let source_kind = channel.source.as_ref().and_then(kind);
+            if let (Some(source), Some(configured)) = (&channel.source, source_kind) {
+                if !is_source(configured) {
+                    mismatches.push(Mismatch::Direction {
+                        channel: channel.name.clone(),
+                        port: source.clone(),
+                        configured,
+                    });
+                }
+            }
+            for destination in &channel.destinations {
+                let Some(configured) = kind(destination) else {
+                    continue;
+                };
+                if is_source(configured) {
+                    mismatches.push(Mismatch::Direction {
+                        channel: channel.name.clone(),
+                        port: destination.clone(),
+                        configured,
+                    });
+                }
+                if let (Some(source), Some(source_kind)) = (&channel.source, source_kind) {
+                    if is_queuing(source_kind) != is_queuing(configured) {
+                        mismatches.push(Mismatch::MixedPorts {
+                            channel: channel.name.clone(),
+                            source: source.clone(),
+                            destination: destination.clone(),
+                        });
+                    }
+                }
+            }
+
+            let Some(source) = &channel.source else {
+                continue;
+            };
+            let Some(source_schema) = schema(source) else {
+                continue;
+            };
+            for destination in &channel.destinations {
+                if schema(destination).is_some_and(|s| s != source_schema) {
+                    mismatches.push(Mismatch::MessageType {
+                        channel: channel.name.clone(),
+                        source: source.clone(),
+                        destination: destination.clone(),
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+}
+
+fn is_source(kind: PortKind) -> bool {
+    matches!(kind, PortKind::QueuingSender | PortKind::SamplingSource)
+}
+
+fn is_queuing(kind: PortKind) -> bool {
+    matches!(kind, PortKind::QueuingSender | PortKind::QueuingReceiver)
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &'static str) -> Result<&'a str, ConfigError> {
+    node.attribute(name)
+        .ok_or_else(|| ConfigError::MissingAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute: name,
+        })
+}
+
+fn parse_attribute<T: core::str::FromStr>(
+    node: Node,
+    name: &'static str,
+) -> Result<T, ConfigError> {
+    let value = attribute(node, name)?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ConfigError::InvalidAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute: name,
+            value: value.to_string(),
+        })
+}
+
+fn parse_partition(node: Node) -> Result<PartitionConfig, ConfigError> {
+    let mut ports = Vec::new();
+    for port in node.children().filter(Node::is_element) {
+        let queuing = match port.tag_name().name() {
+            "Queuing_Port" => true,
+            "Sampling_Port" => false,
+            _ => continue,
+        };
+        let source = match attribute(port, "Direction")? {
+            "SOURCE" => true,
+            "DESTINATION" => false,
+            value => {
+                return Err(ConfigError::InvalidAttribute {
+                    element: port.tag_name().name().to_string(),
+                    attribute: "Direction",
+                    value: value.to_string(),
+                })
+            }
+        };
+        let kind = match (queuing, source) {
+            (true, true) => PortKind::QueuingSender,
+            (true, false) => PortKind::QueuingReceiver,
+            (false, true) => PortKind::SamplingSource,
+            (false, false) => PortKind::SamplingDestination,
+        };
+        ports.push(PortConfig {
+            name: attribute(port, "Name")?.to_string(),
+            kind,
+            max_message_size: parse_attribute(port, "MaxMessageSize")?,
+            max_nb_messages: match queuing {
+                true => Some(parse_attribute(port, "MaxNbMessages")?),
+                false => None,
+            },
+            refresh_ms: match port.attribute("RefreshRateSeconds") {
+                Some(_) if !queuing => Some(parse_refresh(port)?),
+                _ => None,
+            },
+        });
+    }
+    Ok(PartitionConfig {
+        name: attribute(node, "PartitionName")?.to_string(),
+        identifier: node.attribute("PartitionIdentifier").map(str::to_string),
+        ports,
+    })
+}
+
+/// `RefreshRateSeconds` of a sampling port in milliseconds
+fn parse_refresh(node: Node) -> Result<u64, ConfigError> {
+    let seconds: f64 = parse_attribute(node, "RefreshRateSeconds")?;
+    if !(seconds.is_finite() && seconds >= 0.0) {
+        return Err(ConfigError::InvalidAttribute {
+            element: node.tag_name().name().to_string(),
+            attribute: "RefreshRateSeconds",
+            value: attribute(node, "RefreshRateSeconds")?.to_string(),
+        });
+    }
+    Ok((seconds * 1000.0).round() as u64)
+}
+
+/// Port of a channel, referring to its partition by name or identifier
+fn parse_port_ref(node: Node, partitions: &[PartitionConfig]) -> Result<PortRef, ConfigError> {
+    let partition = match node.attribute("PartitionName") {
+        Some(name) => name.to_string(),
+        None => {
+            let id = attribute(node, "PartitionIdentifier")?;
+            partitions
+                .iter()
+                .find(|p| p.identifier.as_deref() == Some(id))
+                .ok_or_else(|| ConfigError::InvalidAttribute {
+                    element: node.tag_name().name().to_string(),
+                    attribute: "PartitionIdentifier",
+                    value: id.to_string(),
+                })?
+                .name
+                .clone()
+        }
+    };
+    Ok(PortRef {
+        partition,
+        port: attribute(node, "PortName")?.to_string(),
+    })
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use a653rs::prelude::{ApexQueuingPortP4Ext, ApexSamplingPortP4Ext};
+    use mock::MockHyp;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    // Only used as hypervisor type
+    #[allow(dead_code, clippy::duplicate_mod)]
+    mod mock;
+
+    const CONFIG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ARINC_653_Module ModuleName="Demo">
+    <Partition PartitionIdentifier="1" PartitionName="Control">
+        <Queuing_Port Name="CMD" Direction="SOURCE" MaxMessageSize="16" MaxNbMessages="8"/>
+        <Sampling_Port Name="POS" Direction="DESTINATION" MaxMessageSize="8" RefreshRateSeconds="0.1"/>
+    </Partition>
+    <Partition PartitionIdentifier="2" PartitionName="Actuator">
+        <Queuing_Port Name="CMD" Direction="DESTINATION" MaxMessageSize="16" MaxNbMessages="4"/>
+        <Sampling_Port Name="POS" Direction="DESTINATION" MaxMessageSize="2"/>
+    </Partition>
+    <Connection_Table>
+        <Channel ChannelIdentifier="1" ChannelName="Commands">
+            <Source><Standard_Partition PartitionIdentifier="1" PortName="CMD"/></Source>
+            <Destination><Standard_Partition PartitionName="Actuator" PortName="CMD"/></Destination>
+        </Channel>
+        <Channel ChannelIdentifier="2">
+            <Source><Pseudo_Partition Name="Sensor"/></Source>
+            <Destination><Standard_Partition PartitionIdentifier="1" PortName="POS"/></Destination>
+        </Channel>
+    </Connection_Table>
+</ARINC_653_Module>"#;
+
+    #[derive(Serialize, Deserialize, MaxSize, Schema)]
+    enum Command {
+        Open,
+        Close,
+    }
+
+    #[derive(Serialize, Deserialize, MaxSize, Schema)]
+    struct Position(u32);
+
+    #[derive(PartitionPorts, PartitionIcd)]
+    struct Control<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "CMD", depth = 8, size = 16)]
+        _cmd: TypedQueuingPortSender<Command, H>,
+        #[sampling(name = "POS", refresh_ms = 100, size = 8)]
+        _pos: TypedSamplingPortDestination<Position, H>,
+    }
+
+    #[derive(PartitionPorts, PartitionIcd)]
+    struct Actuator<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "CMD", depth = 8, size = 16)]
+        _cmd: TypedQueuingPortReceiver<u8, H>,
+        #[sampling(name = "POS")]
+        _pos: TypedSamplingPortSource<Position, H>,
+        #[sampling(name = "STATUS")]
+        _status: TypedSamplingPortSource<bool, H>,
+    }
+
+    #[test]
+    fn config_parse() {
+        let config = ModuleConfig::parse(CONFIG).unwrap();
+        let control = config.partition("Control").unwrap();
+        assert_eq!(Some("1"), control.identifier.as_deref());
+        let cmd = control.port("CMD").unwrap();
+        assert_eq!(PortKind::QueuingSender, cmd.kind);
+        assert_eq!(16, cmd.max_message_size);
+        assert_eq!(Some(8), cmd.max_nb_messages);
+        let pos = control.port("POS").unwrap();
+        assert_eq!(None, pos.max_nb_messages);
+        assert_eq!(Some(100), pos.refresh_ms);
+        assert_eq!(None, cmd.refresh_ms);
+
+        assert_eq!(2, config.channels.len());
+        let commands = &config.channels[0];
+        assert_eq!("Commands", commands.name);
+        assert_eq!("Control", commands.source.as_ref().unwrap().partition);
+        assert_eq!("Actuator", commands.destinations[0].partition);
+        assert_eq!("2", config.channels[1].name);
+        assert_eq!(None, config.channels[1].source);
+
+        assert!(matches!(
+            ModuleConfig::parse("<Partition PartitionName=\"P\"><Queuing_Port Name=\"Q\" Direction=\"SOURCE\" MaxMessageSize=\"many\"/></Partition>"),
+            Err(ConfigError::InvalidAttribute { attribute: "MaxMessageSize", .. })
+        ));
+        assert!(matches!(
+            ModuleConfig::parse("<Partition PartitionName=\"P\"><Sampling_Port Name=\"S\" Direction=\"DESTINATION\" MaxMessageSize=\"4\" RefreshRateSeconds=\"-1\"/></Partition>"),
+            Err(ConfigError::InvalidAttribute { attribute: "RefreshRateSeconds", .. })
+        ));
+        assert!(matches!(
+            ModuleConfig::parse("<Partition/>"),
+            Err(ConfigError::MissingAttribute {
+                attribute: "PartitionName",
+                ..
+            })
+        ));
+        assert!(matches!(
+            ModuleConfig::parse("<Partition>"),
+            Err(ConfigError::Xml(_))
+        ));
+    }
+
+    #[test]
+    fn config_check() {
+        let config = ModuleConfig::parse(CONFIG).unwrap();
+        assert!(config
+            .check(&[("Control", Control::<MockHyp>::icd())])
+            .is_empty());
+
+        let mismatches = config.check(&[
+            ("Control", Control::<MockHyp>::icd()),
+            ("Actuator", Actuator::<MockHyp>::icd()),
+            ("Logger", Icd::new([])),
+        ]);
+        let expected = [
+            Mismatch::Kind {
+                partition: "Actuator".into(),
+                port: "POS".into(),
+                configured: PortKind::SamplingDestination,
+                declared: PortKind::SamplingSource,
+            },
+            Mismatch::MessageSize {
+                partition: "Actuator".into(),
+                port: "POS".into(),
+                configured: 2,
+                max_size: 5,
+            },
+            Mismatch::QueueDepth {
+                partition: "Actuator".into(),
+                port: "CMD".into(),
+                configured: 4,
+                declared: 8,
+            },
+            Mismatch::UnknownPort {
+                partition: "Actuator".into(),
+                port: "STATUS".into(),
+            },
+            Mismatch::UnknownPartition {
+                partition: "Logger".into(),
+            },
+            Mismatch::MessageType {
+                channel: "Commands".into(),
+                source: PortRef {
+                    partition: "Control".into(),
+                    port: "CMD".into(),
+                },
+                destination: PortRef {
+                    partition: "Actuator".into(),
+                    port: "CMD".into(),
+                },
+            },
+        ];
+        assert_eq!(expected.len(), mismatches.len());
+        for mismatch in &expected {
+            assert!(mismatches.contains(mismatch), "{mismatch:?}");
+        }
+
+        let config = ModuleConfig::parse(&CONFIG.replace("0.1", "0.25")).unwrap();
+        let expected = [Mismatch::Refresh {
+            partition: "Control".into(),
+            port: "POS".into(),
+            configured: 250,
+            declared: 100,
+        }];
+        assert_eq!(
+            &expected[..],
+            &config.check(&[("Control", Control::<MockHyp>::icd())])[..]
+        );
+    }
+
+    #[test]
+    fn config_check_channels() {
+        let config = ModuleConfig::parse(
+            r#"<ARINC_653_Module ModuleName="Demo">
+                <Partition PartitionName="A">
+                    <Queuing_Port Name="Q" Direction="SOURCE" MaxMessageSize="4" MaxNbMessages="1"/>
+                    <Sampling_Port Name="S" Direction="DESTINATION" MaxMessageSize="4"/>
+                </Partition>
+                <Partition PartitionName="B">
+                    <Queuing_Port Name="Q" Direction="SOURCE" MaxMessageSize="4" MaxNbMessages="1"/>
+                    <Sampling_Port Name="S" Direction="DESTINATION" MaxMessageSize="4"/>
+                </Partition>
+                <Connection_Table>
+                    <Channel ChannelName="Reversed">
+                        <Source><Standard_Partition PartitionName="A" PortName="S"/></Source>
+                        <Destination><Standard_Partition PartitionName="B" PortName="Q"/></Destination>
+                    </Channel>
+                    <Channel ChannelName="Mixed">
+                        <Source><Standard_Partition PartitionName="A" PortName="Q"/></Source>
+                        <Destination><Standard_Partition PartitionName="B" PortName="S"/></Destination>
+                    </Channel>
+                </Connection_Table>
+            </ARINC_653_Module>"#,
+        )
+        .unwrap();
+        let port = |partition: &str, port: &str| PortRef {
+            partition: partition.into(),
+            port: port.into(),
+        };
+        let expected = [
+            Mismatch::Direction {
+                channel: "Reversed".into(),
+                port: port("A", "S"),
+                configured: PortKind::SamplingDestination,
+            },
+            Mismatch::Direction {
+                channel: "Reversed".into(),
+                port: port("B", "Q"),
+                configured: PortKind::QueuingSender,
+            },
+            Mismatch::MixedPorts {
+                channel: "Reversed".into(),
+                source: port("A", "S"),
+                destination: port("B", "Q"),
+            },
+            Mismatch::MixedPorts {
+                channel: "Mixed".into(),
+                source: port("A", "Q"),
+                destination: port("B", "S"),
+            },
+        ];
+        assert_eq!(&expected[..], &config.check(&[])[..]);
+    }
+}
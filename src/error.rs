@@ -5,80 +5,258 @@ use alloc::vec::Vec;
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::ops::Range;
 
 use a653rs::prelude::*;
 
 #[cfg(feature = "alloc")]
 #[derive(Debug, Clone)]
-pub enum QueuingRecvError {
+#[non_exhaustive]
+pub enum QueuingRecvError<E = postcard::Error> {
     Apex(a653rs::prelude::Error),
-    /// Postcard deserialization error
+    /// Postcard deserialization error, or error of another [`Codec`](crate::codec::Codec)
     ///
     /// Also returns the data which failed to deserialize
-    Postcard(postcard::Error, Vec<u8>),
+    Postcard(E, Vec<u8>),
+    /// The message exceeds the [`Limits`](crate::limits::Limits) of the codec
+    ///
+    /// Also returns the data which failed to deserialize
+    Limit(LimitError, Vec<u8>),
+    /// The received value failed [`Validate::validate`](crate::validate::Validate::validate)
+    ///
+    /// Also returns the data of the invalid value
+    Validation(ValidationError, Vec<u8>),
 }
 
 #[cfg(feature = "alloc")]
-impl From<a653rs::prelude::Error> for QueuingRecvError {
+impl<E> QueuingRecvError<E> {
+    /// Error of decoding `msg` using the codec `C`
+    pub(crate) fn decode<C: crate::codec::Codec<Error = E> + ?Sized>(e: E, msg: Vec<u8>) -> Self {
+        match C::failure(e) {
+            DecodeFailure::Codec(e) => QueuingRecvError::Postcard(e, msg),
+            DecodeFailure::Limit(e) => QueuingRecvError::Limit(e, msg),
+            DecodeFailure::Validation(e) => QueuingRecvError::Validation(e, msg),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E> From<a653rs::prelude::Error> for QueuingRecvError<E> {
     fn from(e: a653rs::prelude::Error) -> Self {
         QueuingRecvError::Apex(e)
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum QueuingRecvBufError<'a> {
+#[non_exhaustive]
+pub enum QueuingRecvBufError<'a, E = postcard::Error> {
     Apex(a653rs::prelude::Error),
-    /// Postcard deserialization error
+    /// Postcard deserialization error, or error of another [`Codec`](crate::codec::Codec)
     ///
     /// Also returns the data which failed to deserialize
-    Postcard(postcard::Error, &'a [u8]),
+    Postcard(E, &'a [u8]),
+    /// The message exceeds the [`Limits`](crate::limits::Limits) of the codec
+    ///
+    /// Also returns the data which failed to deserialize
+    Limit(LimitError, &'a [u8]),
+    /// The received value failed [`Validate::validate`](crate::validate::Validate::validate)
+    ///
+    /// Also returns the data of the invalid value
+    Validation(ValidationError, &'a [u8]),
+}
+
+impl<'a, E> QueuingRecvBufError<'a, E> {
+    /// Error of decoding `msg` using the codec `C`
+    pub(crate) fn decode<C: crate::codec::Codec<Error = E> + ?Sized>(e: E, msg: &'a [u8]) -> Self {
+        match C::failure(e) {
+            DecodeFailure::Codec(e) => QueuingRecvBufError::Postcard(e, msg),
+            DecodeFailure::Limit(e) => QueuingRecvBufError::Limit(e, msg),
+            DecodeFailure::Validation(e) => QueuingRecvBufError::Validation(e, msg),
+        }
+    }
+
+    /// Replace the returned data which failed to deserialize by `msg`
+    pub(crate) fn with_msg(self, msg: &[u8]) -> QueuingRecvBufError<'_, E> {
+        match self {
+            QueuingRecvBufError::Apex(e) => QueuingRecvBufError::Apex(e),
+            QueuingRecvBufError::Postcard(e, _) => QueuingRecvBufError::Postcard(e, msg),
+            QueuingRecvBufError::Limit(e, _) => QueuingRecvBufError::Limit(e, msg),
+            QueuingRecvBufError::Validation(e, _) => QueuingRecvBufError::Validation(e, msg),
+        }
+    }
+
+    /// Data which failed to deserialize, if any
+    pub(crate) fn msg(&self) -> Option<&'a [u8]> {
+        match self {
+            QueuingRecvBufError::Apex(_) => None,
+            QueuingRecvBufError::Postcard(_, msg)
+            | QueuingRecvBufError::Limit(_, msg)
+            | QueuingRecvBufError::Validation(_, msg) => Some(msg),
+        }
+    }
 }
 
-impl From<a653rs::prelude::Error> for QueuingRecvBufError<'_> {
+impl<E> From<a653rs::prelude::Error> for QueuingRecvBufError<'_, E> {
     fn from(e: a653rs::prelude::Error) -> Self {
         QueuingRecvBufError::Apex(e)
     }
 }
 
-#[cfg(feature = "alloc")]
+/// Error of sending a batch using
+/// [`send_batch_buf`](crate::batch::QueuingPortBatchSenderExt::send_batch_buf)
+#[derive(Debug)]
+pub struct BatchSendError {
+    /// Number of items sent before the error, which are the first items of the batch
+    pub sent: usize,
+    /// Error of sending the message, or of serializing the item after the sent ones
+    pub error: SendError,
+}
+
+impl BatchSendError {
+    pub(crate) fn new(sent: usize, error: SendError) -> Self {
+        Self { sent, error }
+    }
+}
+
+/// Error of a single message received by a [`QueuingRecvIter`](crate::queuing::QueuingRecvIter)
+///
+/// The data which failed to deserialize is available from
+/// [`QueuingRecvIter::msg`](crate::queuing::QueuingRecvIter::msg).
+#[derive(Debug, Clone)]
+pub enum QueuingRecvIterError<E = postcard::Error> {
+    Apex(a653rs::prelude::Error),
+    /// Postcard deserialization error, or error of another [`Codec`](crate::codec::Codec)
+    Postcard(E),
+    /// The message exceeds the [`Limits`](crate::limits::Limits) of the codec
+    Limit(LimitError),
+    /// The received value failed [`Validate::validate`](crate::validate::Validate::validate)
+    Validation(ValidationError),
+}
+
+impl<E> QueuingRecvIterError<E> {
+    pub(crate) fn decode<C: crate::codec::Codec<Error = E> + ?Sized>(e: E) -> Self {
+        match C::failure(e) {
+            DecodeFailure::Codec(e) => QueuingRecvIterError::Postcard(e),
+            DecodeFailure::Limit(e) => QueuingRecvIterError::Limit(e),
+            DecodeFailure::Validation(e) => QueuingRecvIterError::Validation(e),
+        }
+    }
+}
+
+/// Error of waiting on a [`Select`](crate::select::Select)
 #[derive(Debug, Clone)]
-pub enum SamplingRecvError {
+pub enum SelectError<'a> {
+    /// Waiting failed or no message arrived in time
     Apex(a653rs::prelude::Error),
+    /// Receiving from the arm with the given index failed
+    Port(usize, QueuingRecvBufError<'a>),
+}
+
+/// Error of forwarding a message using a [`Forwarder`](crate::forward::Forwarder)
+#[derive(Debug)]
+pub enum ForwardError<'a> {
+    /// Receiving from the source port failed
+    Recv(a653rs::prelude::Error),
     /// Postcard deserialization error
     ///
+    /// Also returns the data which failed to deserialize
+    Postcard(postcard::Error, &'a [u8]),
+    /// Sending to the destination port failed
+    Send(SendError),
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SamplingRecvError<E = postcard::Error> {
+    Apex(a653rs::prelude::Error),
+    /// Postcard deserialization error, or error of another [`Codec`](crate::codec::Codec)
+    ///
+    /// Also returns the data which failed to deserialize and its [`Validity`]
+    Postcard(E, Validity, Vec<u8>),
+    /// The message exceeds the [`Limits`](crate::limits::Limits) of the codec
+    ///
     /// Also returns the data which failed to deserialize and its [`Validity`]
-    Postcard(postcard::Error, Validity, Vec<u8>),
+    Limit(LimitError, Validity, Vec<u8>),
+    /// The received value failed [`Validate::validate`](crate::validate::Validate::validate)
+    ///
+    /// Also returns the data of the invalid value and its [`Validity`]
+    Validation(ValidationError, Validity, Vec<u8>),
+}
+
+#[cfg(feature = "alloc")]
+impl<E> SamplingRecvError<E> {
+    /// Error of decoding `msg` using the codec `C`
+    pub(crate) fn decode<C: crate::codec::Codec<Error = E> + ?Sized>(
+        e: E,
+        val: Validity,
+        msg: Vec<u8>,
+    ) -> Self {
+        match C::failure(e) {
+            DecodeFailure::Codec(e) => SamplingRecvError::Postcard(e, val, msg),
+            DecodeFailure::Limit(e) => SamplingRecvError::Limit(e, val, msg),
+            DecodeFailure::Validation(e) => SamplingRecvError::Validation(e, val, msg),
+        }
+    }
 }
 
 #[cfg(feature = "alloc")]
-impl From<a653rs::prelude::Error> for SamplingRecvError {
+impl<E> From<a653rs::prelude::Error> for SamplingRecvError<E> {
     fn from(e: a653rs::prelude::Error) -> Self {
         SamplingRecvError::Apex(e)
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum SamplingRecvBufError<'a> {
+#[non_exhaustive]
+pub enum SamplingRecvBufError<'a, E = postcard::Error> {
     Apex(a653rs::prelude::Error),
-    /// Postcard deserialization error
+    /// Postcard deserialization error, or error of another [`Codec`](crate::codec::Codec)
+    ///
+    /// Also returns the data which failed to deserialize and its [`Validity`]
+    Postcard(E, Validity, &'a [u8]),
+    /// The message exceeds the [`Limits`](crate::limits::Limits) of the codec
     ///
     /// Also returns the data which failed to deserialize and its [`Validity`]
-    Postcard(postcard::Error, Validity, &'a [u8]),
+    Limit(LimitError, Validity, &'a [u8]),
+    /// The received value failed [`Validate::validate`](crate::validate::Validate::validate)
+    ///
+    /// Also returns the data of the invalid value and its [`Validity`]
+    Validation(ValidationError, Validity, &'a [u8]),
+}
+
+impl<'a, E> SamplingRecvBufError<'a, E> {
+    /// Error of decoding `msg` using the codec `C`
+    pub(crate) fn decode<C: crate::codec::Codec<Error = E> + ?Sized>(
+        e: E,
+        val: Validity,
+        msg: &'a [u8],
+    ) -> Self {
+        match C::failure(e) {
+            DecodeFailure::Codec(e) => SamplingRecvBufError::Postcard(e, val, msg),
+            DecodeFailure::Limit(e) => SamplingRecvBufError::Limit(e, val, msg),
+            DecodeFailure::Validation(e) => SamplingRecvBufError::Validation(e, val, msg),
+        }
+    }
 }
 
-impl From<a653rs::prelude::Error> for SamplingRecvBufError<'_> {
+impl<E> From<a653rs::prelude::Error> for SamplingRecvBufError<'_, E> {
     fn from(e: a653rs::prelude::Error) -> Self {
         SamplingRecvBufError::Apex(e)
     }
 }
 
 #[derive(Debug)]
-pub enum SendError {
+pub enum SendError<E = postcard::Error> {
     Apex(a653rs::prelude::Error),
-    Postcard(postcard::Error),
+    /// Postcard serialization error, or error of another [`Codec`](crate::codec::Codec)
+    Postcard(E),
 }
 
-impl From<a653rs::prelude::Error> for SendError {
+impl<E> From<a653rs::prelude::Error> for SendError<E> {
     fn from(e: a653rs::prelude::Error) -> Self {
         SendError::Apex(e)
     }
@@ -89,3 +267,263 @@ impl From<postcard::Error> for SendError {
         SendError::Postcard(e)
     }
 }
+
+/// Decode error of a [`Codec`](crate::codec::Codec), reported in the matching variant of the receive errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeFailure<E> {
+    /// Error reported in the `Postcard` variants
+    Codec(E),
+    /// Violated limit reported in the `Limit` variants
+    Limit(LimitError),
+    /// Violated constraint reported in the `Validation` variants
+    Validation(ValidationError),
+}
+
+/// Error of decoding with [`Limits`](crate::limits::Limits)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// Postcard deserialization error
+    Postcard(postcard::Error),
+    /// A sequence or map has more elements than allowed
+    SequenceLength(usize),
+    /// A string or byte array is longer than allowed
+    StringLength(usize),
+    /// Values are nested deeper than allowed
+    Depth,
+    /// Decoding would allocate more than allowed
+    Allocation(usize),
+}
+
+/// Violated constraint of a [`Validate`](crate::validate::Validate) type
+///
+/// Contains a description of the violated constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationError(pub &'static str);
+
+/// Error of the [`Validated`](crate::validate::Validated) codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidatedError<E> {
+    /// Error of the inner codec
+    Codec(E),
+    /// The value violates its constraints
+    Validation(ValidationError),
+}
+
+impl<E> From<ValidationError> for ValidatedError<E> {
+    fn from(e: ValidationError) -> Self {
+        ValidatedError::Validation(e)
+    }
+}
+
+/// Error of the [`Versioned`](crate::version::Versioned) codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    /// Postcard serialization or deserialization error
+    Postcard(postcard::Error),
+    /// Neither the current version nor any upgrade matches the message version
+    UnknownVersion(u32),
+}
+
+impl From<postcard::Error> for VersionError {
+    fn from(e: postcard::Error) -> Self {
+        VersionError::Postcard(e)
+    }
+}
+
+/// Invalid CCSDS Space Packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpacePacketError {
+    /// Packet version number is not version 1 (`0b000`)
+    InvalidVersion(u8),
+    /// Packet is part of a segmented user data
+    Segmented,
+    /// Length of the packet or data field is invalid
+    InvalidLength(usize),
+    /// Application process identifier exceeds 11 bits
+    InvalidApid(u16),
+    /// Packet sequence count exceeds 14 bits
+    InvalidSequenceCount(u16),
+}
+
+/// Error of sending a Space Packet
+#[derive(Debug)]
+pub enum SpacePacketSendError {
+    Apex(a653rs::prelude::Error),
+    Postcard(postcard::Error),
+    SpacePacket(SpacePacketError),
+}
+
+impl From<a653rs::prelude::Error> for SpacePacketSendError {
+    fn from(e: a653rs::prelude::Error) -> Self {
+        SpacePacketSendError::Apex(e)
+    }
+}
+
+impl From<postcard::Error> for SpacePacketSendError {
+    fn from(e: postcard::Error) -> Self {
+        SpacePacketSendError::Postcard(e)
+    }
+}
+
+impl From<SpacePacketError> for SpacePacketSendError {
+    fn from(e: SpacePacketError) -> Self {
+        SpacePacketSendError::SpacePacket(e)
+    }
+}
+
+/// Error of receiving a Space Packet
+#[derive(Debug, Clone)]
+pub enum SpacePacketRecvBufError<'a> {
+    Apex(a653rs::prelude::Error),
+    /// Invalid Space Packet
+    ///
+    /// Also returns the packet
+    SpacePacket(SpacePacketError, &'a [u8]),
+    /// Postcard deserialization error
+    ///
+    /// Also returns the packet data field which failed to deserialize
+    Postcard(postcard::Error, &'a [u8]),
+}
+
+impl From<a653rs::prelude::Error> for SpacePacketRecvBufError<'_> {
+    fn from(e: a653rs::prelude::Error) -> Self {
+        SpacePacketRecvBufError::Apex(e)
+    }
+}
+
+/// Error of the [`PodCodec`](crate::pod::PodCodec)
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PodError {
+    /// The buffer is smaller than the type
+    BufferFull,
+    /// The message size does not match the size of the type
+    InvalidSize { expected: usize, actual: usize },
+    /// The message is not a valid bit pattern of the type
+    InvalidBitPattern,
+}
+
+/// Error of reading or writing a byte stream over a queuing port
+#[cfg(any(feature = "embedded-io", feature = "std"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum IoError {
+    Apex(a653rs::prelude::Error),
+}
+
+#[cfg(any(feature = "embedded-io", feature = "std"))]
+impl From<a653rs::prelude::Error> for IoError {
+    fn from(e: a653rs::prelude::Error) -> Self {
+        IoError::Apex(e)
+    }
+}
+
+/// Error of polling a [`UdpBridge`](crate::udp::UdpBridge)
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum BridgeError {
+    Apex(a653rs::prelude::Error),
+    /// Socket error
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl From<a653rs::prelude::Error> for BridgeError {
+    fn from(e: a653rs::prelude::Error) -> Self {
+        BridgeError::Apex(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for BridgeError {
+    fn from(e: std::io::Error) -> Self {
+        BridgeError::Io(e)
+    }
+}
+
+/// Error of parsing a [`ModuleConfig`](crate::config::ModuleConfig)
+#[cfg(feature = "config")]
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The document is not well-formed XML
+    Xml(roxmltree::Error),
+    /// A required attribute is missing
+    MissingAttribute {
+        element: std::string::String,
+        attribute: &'static str,
+    },
+    /// An attribute has an invalid value
+    InvalidAttribute {
+        element: std::string::String,
+        attribute: &'static str,
+        value: std::string::String,
+    },
+}
+
+#[cfg(feature = "config")]
+impl From<roxmltree::Error> for ConfigError {
+    fn from(e: roxmltree::Error) -> Self {
+        ConfigError::Xml(e)
+    }
+}
+
+/// Error of decoding a message using [`inspect`](crate::inspect::inspect)
+#[cfg(feature = "icd")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectError {
+    /// Postcard deserialization error
+    pub error: postcard::Error,
+    /// Offset of the first byte which could not be decoded
+    pub position: usize,
+    /// Path of the value which could not be decoded, e.g. `.samples[3]`
+    pub path: std::string::String,
+}
+
+/// Error of sending or receiving a type using [`UdpSocketExt`](crate::udp::UdpSocketExt)
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum UdpError {
+    /// Socket error
+    Io(std::io::Error),
+    /// Postcard (de)serialization error
+    Postcard(postcard::Error),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for UdpError {
+    fn from(e: std::io::Error) -> Self {
+        UdpError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<postcard::Error> for UdpError {
+    fn from(e: postcard::Error) -> Self {
+        UdpError::Postcard(e)
+    }
+}
+
+/// Location of a buffer, for finding messages borrowed from it
+///
+/// Functions failing on a message keep its range instead of the message itself,
+/// as returning a message borrowed from the buffer conditionally keeps the buffer borrowed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BufBounds {
+    start: usize,
+    len: usize,
+}
+
+impl BufBounds {
+    pub(crate) fn of(buf: &[u8]) -> Self {
+        Self {
+            start: buf.as_ptr() as usize,
+            len: buf.len(),
+        }
+    }
+
+    /// Range of `msg` within the buffer, if it is a part of it
+    pub(crate) fn range(self, msg: &[u8]) -> Option<Range<usize>> {
+        let start = (msg.as_ptr() as usize).checked_sub(self.start)?;
+        let range = start..start + msg.len();
+        (range.end <= self.len).then_some(range)
+    }
+}
@@ -0,0 +1,194 @@
+//! Publishing one value to many ports
+
+use a653rs::prelude::*;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::Serialize;
+
+/// Port accepting already serialized messages
+pub trait MessageSender {
+    /// Send the serialized message `msg`
+    ///
+    /// `timeout` is ignored by ports which do not block.
+    fn send_msg(&self, msg: &[u8], timeout: SystemTime) -> Result<(), Error>;
+}
+
+impl<Q: ApexQueuingPortP4Ext> MessageSender for QueuingPortSender<Q> {
+    fn send_msg(&self, msg: &[u8], timeout: SystemTime) -> Result<(), Error> {
+        self.send(msg, timeout)
+    }
+}
+
+impl<S: ApexSamplingPortP4Ext> MessageSender for SamplingPortSource<S> {
+    fn send_msg(&self, msg: &[u8], _timeout: SystemTime) -> Result<(), Error> {
+        self.send(msg)
+    }
+}
+
+/// Sends a value to `N` ports, serializing it only once
+///
+/// Ports may be any mix of [`QueuingPortSender`]s and [`SamplingPortSource`]s.
+#[derive(Clone, Copy)]
+pub struct FanOut<'a, const N: usize> {
+    ports: [&'a dyn MessageSender; N],
+}
+
+impl<'a, const N: usize> FanOut<'a, N> {
+    /// Send to all `ports`
+    pub fn new(ports: [&'a dyn MessageSender; N]) -> Self {
+        Self { ports }
+    }
+
+    /// Send a type to all ports
+    ///
+    /// Requires a buffer `buf` for serialization.
+    /// The value is sent to every port, even if sending to a previous port failed.
+    /// Queuing ports are sent to one after another, each waiting up to `timeout`.
+    /// Returns the result of sending to each port.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let queuing = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("A").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let sampling = ctx
+    /// #     .create_sampling_port_source(Name::from_str("B").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let queuing: QueuingPortSender<Hypervisor> = queuing;
+    /// let sampling: SamplingPortSource<Hypervisor> = sampling;
+    /// let fan_out = FanOut::new([&queuing, &sampling]);
+    /// let mut buf = [0; 500];
+    /// let results = fan_out
+    ///     .send_type_buf(String::from("Telemetry"), SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// assert!(results.iter().all(Result::is_ok));
+    /// # })
+    /// ```
+    pub fn send_type_buf<T>(
+        &self,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<[Result<(), Error>; N], postcard::Error>
+    where
+        T: Serialize,
+    {
+        let msg =
+            postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(&p, SerSlice::new(buf))?;
+        Ok(self.send_msg(msg, timeout))
+    }
+
+    /// Send a type to all ports
+    ///
+    /// The value is sent to every port, even if sending to a previous port failed.
+    /// Queuing ports are sent to one after another, each waiting up to `timeout`.
+    /// Returns the result of sending to each port.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let queuing = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("A").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let sampling = ctx
+    /// #     .create_sampling_port_source(Name::from_str("B").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let queuing: QueuingPortSender<Hypervisor> = queuing;
+    /// let sampling: SamplingPortSource<Hypervisor> = sampling;
+    /// let fan_out = FanOut::new([&queuing, &sampling]);
+    /// let results = fan_out
+    ///     .send_type(String::from("Telemetry"), SystemTime::Infinite)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn send_type<T>(
+        &self,
+        p: T,
+        timeout: SystemTime,
+    ) -> Result<[Result<(), Error>; N], postcard::Error>
+    where
+        T: Serialize,
+    {
+        let msg = postcard::to_allocvec(&p)?;
+        Ok(self.send_msg(&msg, timeout))
+    }
+
+    /// Send an already serialized message to all ports
+    pub fn send_msg(&self, msg: &[u8], timeout: SystemTime) -> [Result<(), Error>; N] {
+        self.ports.map(|port| port.send_msg(msg, timeout.clone()))
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Error, Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn fan_out_mixed() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["A", "B", "C"].map(|n| Name::from_str(n).unwrap());
+            let src_a = ctx
+                .create_queuing_port_sender(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let src_b = ctx
+                .create_sampling_port_source(names[1].clone(), 100)
+                .unwrap();
+            let src_c = ctx
+                .create_sampling_port_source(names[2].clone(), 2)
+                .unwrap();
+            let dest_a = ctx
+                .create_queuing_port_receiver(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let dest_b = ctx
+                .create_sampling_port_destination(names[1].clone(), 100, Duration::ZERO)
+                .unwrap();
+            let mut buf = [0; 100];
+
+            let fan_out = FanOut::new([&src_a, &src_b, &src_c]);
+            let results = fan_out
+                .send_type_buf(100_000u32, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert!(results[0].is_ok());
+            assert!(results[1].is_ok());
+            // Port `C` is too small for the message
+            assert_eq!(Err(Error::InvalidConfig), results[2]);
+
+            let (rec, _) = dest_a
+                .recv_type_buf::<u32>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(100_000, rec);
+            let (_, rec) = dest_b.recv_type_buf::<u32>(&mut buf).unwrap();
+            assert_eq!(100_000, rec);
+        })
+    }
+}
@@ -0,0 +1,302 @@
+//! Forwarding typed values from one port to another
+
+use core::marker::PhantomData;
+
+use a653rs::prelude::*;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+use crate::fan_out::MessageSender;
+
+/// Additional information of a received message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiveInfo {
+    /// Message received from a queuing port, whose queue may have overflowed
+    Queuing(QueueOverflow),
+    /// Message received from a sampling port with the given [`Validity`]
+    Sampling(Validity),
+}
+
+/// Port providing serialized messages
+pub trait MessageReceiver {
+    /// Receive a serialized message into `buf`
+    ///
+    /// `timeout` is ignored by ports which do not block.
+    /// The returned message should be part of `buf`, otherwise a message which fails to
+    /// deserialize can not be returned in a [`ForwardError`].
+    fn recv_msg<'a>(
+        &self,
+        buf: &'a mut [u8],
+        timeout: SystemTime,
+    ) -> Result<(&'a [u8], ReceiveInfo), Error>;
+}
+
+impl<Q: ApexQueuingPortP4Ext> MessageReceiver for QueuingPortReceiver<Q> {
+    fn recv_msg<'a>(
+        &self,
+        buf: &'a mut [u8],
+        timeout: SystemTime,
+    ) -> Result<(&'a [u8], ReceiveInfo), Error> {
+        let (msg, overflow) = self.receive(buf, timeout)?;
+        Ok((msg, ReceiveInfo::Queuing(overflow)))
+    }
+}
+
+impl<S: ApexSamplingPortP4Ext> MessageReceiver for SamplingPortDestination<S> {
+    fn recv_msg<'a>(
+        &self,
+        buf: &'a mut [u8],
+        _timeout: SystemTime,
+    ) -> Result<(&'a [u8], ReceiveInfo), Error> {
+        let (validity, msg) = self.receive(buf)?;
+        Ok((msg, ReceiveInfo::Sampling(validity)))
+    }
+}
+
+/// Result of forwarding a single message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Forwarded {
+    /// Information of the received message
+    pub info: ReceiveInfo,
+    /// Whether the message was sent, i.e. it was not filtered out
+    pub sent: bool,
+}
+
+/// Forwards values of type `T` from a receiving port to a sending port
+///
+/// Received values are passed to a mapping closure together with their [`ReceiveInfo`],
+/// which may transform them into a type `U` or filter them by returning `None`.
+/// Ports may be any mix of queuing and sampling ports.
+pub struct Forwarder<'a, T, U, F> {
+    source: &'a dyn MessageReceiver,
+    destination: &'a dyn MessageSender,
+    map: F,
+    _t: PhantomData<fn(T) -> U>,
+}
+
+impl<'a, T> Forwarder<'a, T, T, fn(T, ReceiveInfo) -> Option<T>> {
+    /// Forward all values from `source` to `destination` unchanged
+    pub fn new(source: &'a dyn MessageReceiver, destination: &'a dyn MessageSender) -> Self {
+        Self::with_map(source, destination, |t, _| Some(t))
+    }
+}
+
+impl<'a, T, U, F> Forwarder<'a, T, U, F> {
+    /// Forward values from `source` to `destination`, mapping or filtering them with `map`
+    pub fn with_map(
+        source: &'a dyn MessageReceiver,
+        destination: &'a dyn MessageSender,
+        map: F,
+    ) -> Self {
+        Self {
+            source,
+            destination,
+            map,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, U, F> Forwarder<'_, T, U, F>
+where
+    T: for<'b> Deserialize<'b>,
+    U: Serialize,
+    F: FnMut(T, ReceiveInfo) -> Option<U>,
+{
+    /// Forward a single message
+    ///
+    /// Requires a buffer `buf` for receiving, deserializing and serializing the data.
+    /// `timeout` is used for both receiving and sending on queuing ports.
+    /// Returns the [`ReceiveInfo`] of the received message and whether it was sent.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src = ctx
+    /// #     .create_sampling_port_source(Name::from_str("IN").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let input = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("IN").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    /// # let output = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("OUT").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # src.send_type_buf(21u32, &mut buf).unwrap();
+    ///
+    /// let input: SamplingPortDestination<Hypervisor> = input;
+    /// let output: QueuingPortSender<Hypervisor> = output;
+    /// // Only forward valid samples, scaled by two
+    /// let mut forwarder = Forwarder::with_map(&input, &output, |value: u32, info| {
+    ///     (info == ReceiveInfo::Sampling(Validity::Valid)).then_some(value * 2)
+    /// });
+    /// let forwarded = forwarder
+    ///     .forward_buf(SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// assert!(forwarded.sent);
+    /// # })
+    /// ```
+    pub fn forward_buf<'b>(
+        &mut self,
+        timeout: SystemTime,
+        buf: &'b mut [u8],
+    ) -> Result<Forwarded, ForwardError<'b>> {
+        let bounds = BufBounds::of(buf);
+        let (msg, info) = self
+            .source
+            .recv_msg(buf, timeout.clone())
+            .map_err(ForwardError::Recv)?;
+        let value = match postcard::from_bytes::<T>(msg) {
+            Ok(value) => value,
+            Err(e) => {
+                let range = bounds.range(msg);
+                let msg = range.and_then(|r| buf.get(r)).unwrap_or_default();
+                return Err(ForwardError::Postcard(e, msg));
+            }
+        };
+
+        let Some(value) = (self.map)(value, info) else {
+            return Ok(Forwarded { info, sent: false });
+        };
+        let msg =
+            postcard::serialize_with_flavor::<U, SerSlice, &mut [u8]>(&value, SerSlice::new(buf))
+                .map_err(|e| ForwardError::Send(e.into()))?;
+        self.destination
+            .send_msg(msg, timeout)
+            .map_err(|e| ForwardError::Send(e.into()))?;
+        Ok(Forwarded { info, sent: true })
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+    use std::string::String;
+
+    use a653rs::bindings::{QueuingDiscipline, Validity};
+    use a653rs::prelude::{Error, Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn forward_queuing_filter() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["IN", "OUT"].map(|n| Name::from_str(n).unwrap());
+            let [in_src, out_src] = names.clone().map(|name| {
+                ctx.create_queuing_port_sender(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let [in_dest, out_dest] = names.map(|name| {
+                ctx.create_queuing_port_receiver(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let mut buf = [0; 100];
+
+            let mut forwarder = Forwarder::with_map(&in_dest, &out_src, |value: u32, _| {
+                (value % 2 == 0).then(|| String::from("Even"))
+            });
+            in_src
+                .send_types_buf([1u32, 2], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            let forwarded = forwarder.forward_buf(timeout.clone(), &mut buf).unwrap();
+            assert_eq!(ReceiveInfo::Queuing(false), forwarded.info);
+            assert!(!forwarded.sent);
+            assert!(
+                forwarder
+                    .forward_buf(timeout.clone(), &mut buf)
+                    .unwrap()
+                    .sent
+            );
+            assert!(matches!(
+                forwarder.forward_buf(timeout.clone(), &mut buf),
+                Err(ForwardError::Recv(Error::NotAvailable))
+            ));
+
+            let (rec, _) = out_dest.recv_type_buf::<String>(timeout, &mut buf).unwrap();
+            assert_eq!("Even", rec);
+        })
+    }
+
+    #[test]
+    fn forward_sampling() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["IN", "OUT"].map(|n| Name::from_str(n).unwrap());
+            let [in_src, out_src] = names
+                .clone()
+                .map(|name| ctx.create_sampling_port_source(name, 100).unwrap());
+            let [in_dest, out_dest] = names.map(|name| {
+                ctx.create_sampling_port_destination(name, 100, Duration::ZERO)
+                    .unwrap()
+            });
+            let mut buf = [0; 100];
+
+            let mut forwarder = Forwarder::<u32, _, _>::new(&in_dest, &out_src);
+            in_src.send_type_buf(7u32, &mut buf).unwrap();
+            MockHyp::set_validity(in_dest.id(), Validity::Invalid);
+            let forwarded = forwarder
+                .forward_buf(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(ReceiveInfo::Sampling(Validity::Invalid), forwarded.info);
+            let (_, rec) = out_dest.recv_type_buf::<u32>(&mut buf).unwrap();
+            assert_eq!(7, rec);
+
+            in_src.send_type_buf(u64::MAX, &mut buf).unwrap();
+            assert!(matches!(
+                forwarder.forward_buf(SystemTime::Infinite, &mut buf),
+                Err(ForwardError::Postcard(_, msg)) if msg.len() == 10
+            ));
+        })
+    }
+
+    /// Receiver returning its message at an offset within the buffer
+    struct Offset;
+
+    impl MessageReceiver for Offset {
+        fn recv_msg<'a>(
+            &self,
+            buf: &'a mut [u8],
+            _timeout: SystemTime,
+        ) -> Result<(&'a [u8], ReceiveInfo), Error> {
+            buf[..4].copy_from_slice(&[0xAA, 0xFF, 0xFF, 0xFF]);
+            Ok((&buf[1..4], ReceiveInfo::Queuing(false)))
+        }
+    }
+
+    #[test]
+    fn forward_offset_message() {
+        MockHyp::run_test(|mut ctx| {
+            let out_src = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("OUT").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 100];
+
+            let mut forwarder = Forwarder::<u32, _, _>::new(&Offset, &out_src);
+            assert!(matches!(
+                forwarder.forward_buf(SystemTime::Infinite, &mut buf),
+                Err(ForwardError::Postcard(_, [0xFF, 0xFF, 0xFF]))
+            ));
+        })
+    }
+}
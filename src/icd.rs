@@ -0,0 +1,285 @@
+//! Interface control documents generated from port declarations
+//!
+//! Structs deriving [`PartitionIcd`] next to [`PartitionPorts`](crate::ports::PartitionPorts)
+//! describe every port with its configuration and the [`Schema`] of its message type.
+//! The description is exported as JSON, so that ICDs are derived from the Rust types
+//! actually sent instead of being maintained by hand.
+//!
+//! The [`Schema`] derive macro refers to the `postcard_schema` crate.
+//! Crates not depending on it directly select the re-export instead,
+//! using `#[postcard(crate = a653rs_postcard::icd::postcard_schema)]`.
+
+extern crate std;
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use a653rs::prelude::*;
+pub use a653rs_postcard_derive::PartitionIcd;
+use postcard_schema::schema::owned::OwnedNamedType;
+use postcard_schema::schema::NamedType;
+pub use postcard_schema::{self, Schema};
+use serde::{Serialize, Serializer};
+
+use crate::ports::{
+    MaxSize, TypedQueuingPortReceiver, TypedQueuingPortSender, TypedSamplingPortDestination,
+    TypedSamplingPortSource,
+};
+
+/// Kind and direction of a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortKind {
+    QueuingSender,
+    QueuingReceiver,
+    SamplingSource,
+    SamplingDestination,
+}
+
+/// Typed port which can be described in an [`Icd`]
+pub trait DescribePort {
+    /// Kind and direction of the port
+    const KIND: PortKind;
+    /// Maximum size of a postcard-encoded message in bytes
+    const MAX_SIZE: usize;
+    /// Schema of the message type
+    const SCHEMA: &'static NamedType;
+}
+
+impl<T, Q, C> DescribePort for TypedQueuingPortSender<T, Q, C>
+where
+    T: Schema + MaxSize,
+    Q: ApexQueuingPortP4Ext,
+{
+    const KIND: PortKind = PortKind::QueuingSender;
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+    const SCHEMA: &'static NamedType = T::SCHEMA;
+}
+
+impl<T, Q, C> DescribePort for TypedQueuingPortReceiver<T, Q, C>
+where
+    T: Schema + MaxSize,
+    Q: ApexQueuingPortP4Ext,
+{
+    const KIND: PortKind = PortKind::QueuingReceiver;
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+    const SCHEMA: &'static NamedType = T::SCHEMA;
+}
+
+impl<T, S, C> DescribePort for TypedSamplingPortSource<T, S, C>
+where
+    T: Schema + MaxSize,
+    S: ApexSamplingPortP4Ext,
+{
+    const KIND: PortKind = PortKind::SamplingSource;
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+    const SCHEMA: &'static NamedType = T::SCHEMA;
+}
+
+impl<T, S, C> DescribePort for TypedSamplingPortDestination<T, S, C>
+where
+    T: Schema + MaxSize,
+    S: ApexSamplingPortP4Ext,
+{
+    const KIND: PortKind = PortKind::SamplingDestination;
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+    const SCHEMA: &'static NamedType = T::SCHEMA;
+}
+
+/// Description of a single port and its message type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortDescription {
+    /// Port name
+    pub name: &'static str,
+    /// Kind and direction of the port
+    pub kind: PortKind,
+    /// Maximum number of queued messages of a queuing port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<MessageRange>,
+    /// Queuing discipline of a queuing port, exported as either `fifo` or `priority`
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_discipline"
+    )]
+    pub discipline: Option<QueuingDiscipline>,
+    /// Refresh period of a sampling port destination in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_ms: Option<u64>,
+    /// Message size of the port in bytes, by default the maximum size of a postcard-encoded message
+    pub max_size: usize,
+    /// Rust-like notation of the message type
+    pub message: String,
+    /// Postcard schema of the message type
+    pub schema: &'static NamedType,
+}
+
+impl PortDescription {
+    /// Describe the queuing port `P`
+    pub fn queuing<P: DescribePort>(
+        name: &'static str,
+        depth: MessageRange,
+        discipline: QueuingDiscipline,
+    ) -> Self {
+        Self {
+            depth: Some(depth),
+            discipline: Some(discipline),
+            ..Self::new::<P>(name)
+        }
+    }
+
+    /// Describe the sampling port `P`
+    pub fn sampling<P: DescribePort>(name: &'static str, refresh_ms: Option<u64>) -> Self {
+        Self {
+            refresh_ms,
+            ..Self::new::<P>(name)
+        }
+    }
+
+    /// Override the message size of the port
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    fn new<P: DescribePort>(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: P::KIND,
+            depth: None,
+            discipline: None,
+            refresh_ms: None,
+            max_size: P::MAX_SIZE,
+            message: OwnedNamedType::from(P::SCHEMA).to_string(),
+            schema: P::SCHEMA,
+        }
+    }
+}
+
+/// Serialize a queuing discipline like the `discipline` attribute of the derive macros
+fn serialize_discipline<S: Serializer>(
+    discipline: &Option<QueuingDiscipline>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name = discipline.map(|discipline| match discipline {
+        QueuingDiscipline::Fifo => "fifo",
+        QueuingDiscipline::Priority => "priority",
+    });
+    name.serialize(serializer)
+}
+
+/// Interface control document of a partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Icd {
+    /// Every port of the partition
+    pub ports: Vec<PortDescription>,
+}
+
+impl Icd {
+    /// Document describing `ports`
+    pub fn new(ports: impl IntoIterator<Item = PortDescription>) -> Self {
+        Self {
+            ports: ports.into_iter().collect(),
+        }
+    }
+
+    /// Description of the port called `name`
+    pub fn port(&self, name: &str) -> Option<&PortDescription> {
+        self.ports.iter().find(|p| p.name == name)
+    }
+
+    /// Export as pretty-printed JSON
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Partition interface able to describe its ports
+///
+/// Derived using the port attributes of [`PartitionPorts`](crate::ports::PartitionPorts),
+/// requiring every message type to implement [`Schema`] and [`MaxSize`].
+/// Only structs deriving it are described,
+/// so partitions with message types lacking a [`Schema`] still derive [`PartitionPorts`](crate::ports::PartitionPorts).
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, MaxSize, Schema)]
+/// #[postcard(crate = a653rs_postcard::icd::postcard_schema)]
+/// enum Command {
+///     Start,
+///     Stop,
+/// }
+///
+/// #[derive(PartitionPorts, PartitionIcd)]
+/// struct Ports<H: ApexQueuingPortP4Ext> {
+///     #[queuing(name = "CMD", depth = 16)]
+///     cmd: TypedQueuingPortReceiver<Command, H>,
+/// }
+///
+/// let icd = Ports::<Hypervisor>::icd();
+/// assert_eq!("enum Command { Start, Stop }", icd.port("CMD").unwrap().message);
+/// println!("{}", icd.to_json().unwrap());
+/// ```
+pub trait PartitionIcd {
+    /// Describe every port
+    fn icd() -> Icd;
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use a653rs::prelude::{ApexQueuingPortP4Ext, ApexSamplingPortP4Ext, QueuingDiscipline};
+    use mock::MockHyp;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    // Only used as hypervisor type
+    #[allow(dead_code, clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Serialize, Deserialize, MaxSize, Schema)]
+    struct Housekeeping {
+        voltage: u16,
+        temperatures: [i8; 4],
+    }
+
+    #[derive(PartitionPorts, PartitionIcd)]
+    struct Ports<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "EVENTS", depth = 8, discipline = "priority")]
+        _events: TypedQueuingPortSender<u32, H>,
+        #[sampling(name = "HK", refresh_ms = 250, size = 16)]
+        _housekeeping: TypedSamplingPortDestination<Housekeeping, H>,
+    }
+
+    #[test]
+    fn icd_export() {
+        let icd = Ports::<MockHyp>::icd();
+        let events = icd.port("EVENTS").unwrap();
+        assert_eq!(PortKind::QueuingSender, events.kind);
+        assert_eq!(Some(8), events.depth);
+        assert_eq!(Some(QueuingDiscipline::Priority), events.discipline);
+        assert_eq!(5, events.max_size);
+        assert_eq!("u32", events.message);
+
+        let housekeeping = icd.port("HK").unwrap();
+        assert_eq!(PortKind::SamplingDestination, housekeeping.kind);
+        assert_eq!(None, housekeeping.depth);
+        assert_eq!(Some(250), housekeeping.refresh_ms);
+        assert_eq!(16, housekeeping.max_size);
+
+        let json: serde_json::Value = serde_json::from_str(&icd.to_json().unwrap()).unwrap();
+        assert_eq!("sampling_destination", json["ports"][1]["kind"]);
+        assert_eq!("Housekeeping", json["ports"][1]["schema"]["name"]);
+        assert!(json["ports"][1].get("depth").is_none());
+        assert_eq!("priority", json["ports"][0]["discipline"]);
+    }
+}
@@ -0,0 +1,466 @@
+//! Decoding of messages without knowing their Rust type
+//!
+//! Messages are decoded using their postcard [`OwnedNamedType`] schema, e.g. taken from an
+//! exported [`Icd`](crate::icd::Icd), into a [`Value`] which is printed as JSON or like the
+//! original type. Failures report the position of the offending byte,
+//! so that the bytes of a [`QueuingRecvBufError::Postcard`](crate::error::QueuingRecvBufError)
+//! can be inspected.
+
+extern crate std;
+
+use core::fmt::{self, Debug, Formatter};
+use std::borrow::ToOwned;
+use std::boxed::Box;
+use std::format;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+pub use postcard_schema::schema::owned::OwnedNamedType;
+use postcard_schema::schema::owned::{OwnedDataModelType, OwnedDataModelVariant};
+use serde::Deserialize;
+use serde_json::{Map, Number};
+
+use crate::error::InspectError;
+
+/// Decoded value of a message
+///
+/// The [`Debug`] output of a value matches the one of a derived [`Debug`] of its original type.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i128),
+    Uint(u128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Value>>),
+    Unit,
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    /// Struct with its name
+    Struct(String, Fields),
+    /// Enum variant with the name of the enum and the variant
+    Variant(String, String, Fields),
+}
+
+/// Fields of a struct or an enum variant
+#[derive(Clone, PartialEq)]
+pub enum Fields {
+    Unit,
+    Tuple(Vec<Value>),
+    Named(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Convert to JSON, as serialized by `serde_json` from the original type
+    ///
+    /// Integers not fitting 64 bits are converted to strings, map keys to their [`Debug`] output.
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as Json;
+        match self {
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Int(i) => {
+                i64::try_from(*i).map_or_else(|_| Json::String(i.to_string()), Json::from)
+            }
+            Value::Uint(u) => {
+                u64::try_from(*u).map_or_else(|_| Json::String(u.to_string()), Json::from)
+            }
+            Value::F32(f) => Json::from(*f),
+            Value::F64(f) => Number::from_f64(*f).map_or(Json::Null, Json::Number),
+            Value::Char(c) => Json::String(c.to_string()),
+            Value::String(s) => Json::String(s.clone()),
+            Value::Bytes(b) => Json::from(b.as_slice()),
+            Value::Option(o) => o.as_ref().map_or(Json::Null, |v| v.to_json()),
+            Value::Unit => Json::Null,
+            Value::Seq(values) | Value::Tuple(values) => {
+                Json::Array(values.iter().map(Value::to_json).collect())
+            }
+            Value::Map(entries) => Json::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = match k {
+                            Value::String(s) => s.clone(),
+                            k => format!("{k:?}"),
+                        };
+                        (key, v.to_json())
+                    })
+                    .collect(),
+            ),
+            Value::Struct(_, fields) => fields.to_json(),
+            Value::Variant(_, variant, Fields::Unit) => Json::String(variant.clone()),
+            Value::Variant(_, variant, fields) => {
+                Json::Object(Map::from_iter([(variant.clone(), fields.to_json())]))
+            }
+        }
+    }
+}
+
+impl Fields {
+    fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as Json;
+        match self {
+            Fields::Unit => Json::Null,
+            Fields::Tuple(values) if values.len() == 1 => values[0].to_json(),
+            Fields::Tuple(values) => Json::Array(values.iter().map(Value::to_json).collect()),
+            Fields::Named(fields) => Json::Object(
+                fields
+                    .iter()
+                    .map(|(name, v)| (name.clone(), v.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn fmt(&self, name: &str, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Fields::Unit => f.write_str(name),
+            Fields::Tuple(values) => values
+                .iter()
+                .fold(&mut f.debug_tuple(name), |t, v| t.field(v))
+                .finish(),
+            Fields::Named(fields) => fields
+                .iter()
+                .fold(&mut f.debug_struct(name), |s, (n, v)| s.field(n, v))
+                .finish(),
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => b.fmt(f),
+            Value::Int(i) => i.fmt(f),
+            Value::Uint(u) => u.fmt(f),
+            Value::F32(x) => x.fmt(f),
+            Value::F64(x) => x.fmt(f),
+            Value::Char(c) => c.fmt(f),
+            Value::String(s) => s.fmt(f),
+            Value::Bytes(b) => b.fmt(f),
+            Value::Option(o) => o.fmt(f),
+            Value::Unit => f.write_str("()"),
+            Value::Seq(values) => f.debug_list().entries(values).finish(),
+            Value::Tuple(values) => values
+                .iter()
+                .fold(&mut f.debug_tuple(""), |t, v| t.field(v))
+                .finish(),
+            Value::Map(entries) => f
+                .debug_map()
+                .entries(entries.iter().map(|(k, v)| (k, v)))
+                .finish(),
+            Value::Struct(name, fields) => fields.fmt(name, f),
+            Value::Variant(ty, variant, fields) => fields.fmt(&format!("{ty}::{variant}"), f),
+        }
+    }
+}
+
+/// Decode a single message of type `schema` from the start of `bytes`
+///
+/// Returns the decoded value and the remaining bytes.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize, Schema)]
+/// struct Position {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let schema = OwnedNamedType::from(Position::SCHEMA);
+/// let bytes = postcard::to_allocvec(&Position { x: 1, y: -1 }).unwrap();
+/// let (value, _) = inspect(&schema, &bytes).unwrap();
+/// assert_eq!("Position { x: 1, y: -1 }", format!("{value:?}"));
+/// assert_eq!(r#"{"x":1,"y":-1}"#, value.to_json().to_string());
+///
+/// let err = inspect(&schema, &bytes[..1]).unwrap_err();
+/// assert_eq!((1, ".y"), (err.position, err.path.as_str()));
+/// ```
+pub fn inspect<'a>(
+    schema: &OwnedNamedType,
+    bytes: &'a [u8],
+) -> Result<(Value, &'a [u8]), InspectError> {
+    let mut decoder = Decoder {
+        rest: bytes,
+        position: 0,
+        path: String::new(),
+    };
+    let value = decoder.value(schema)?;
+    Ok((value, decoder.rest))
+}
+
+/// Schema of the messages of the port called `port` in an exported [`Icd`](crate::icd::Icd)
+pub fn icd_schema(icd_json: &str, port: &str) -> serde_json::Result<Option<OwnedNamedType>> {
+    let icd: serde_json::Value = serde_json::from_str(icd_json)?;
+    icd["ports"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|p| p["name"] == port)
+        .map(|p| OwnedNamedType::deserialize(&p["schema"]))
+        .transpose()
+}
+
+struct Decoder<'a> {
+    rest: &'a [u8],
+    position: usize,
+    path: String,
+}
+
+impl<'a> Decoder<'a> {
+    fn error(&self, error: postcard::Error) -> InspectError {
+        InspectError {
+            error,
+            position: self.position,
+            path: self.path.clone(),
+        }
+    }
+
+    fn take<T: Deserialize<'a>>(&mut self) -> Result<T, InspectError> {
+        let (value, rest) = postcard::take_from_bytes(self.rest).map_err(|e| self.error(e))?;
+        self.position += self.rest.len() - rest.len();
+        self.rest = rest;
+        Ok(value)
+    }
+
+    /// Take the length of a sequence or map
+    fn len(&mut self) -> Result<usize, InspectError> {
+        let start = (self.rest, self.position);
+        let len = self.take::<usize>()?;
+        // Every element takes at least one byte, except for those of unit types
+        // which are not worth decoding in arbitrary numbers
+        if len > self.rest.len() {
+            (self.rest, self.position) = start;
+            return Err(self.error(postcard::Error::DeserializeUnexpectedEnd));
+        }
+        Ok(len)
+    }
+
+    /// Decode `schema` as the value at `segment` of the current path
+    fn nested(&mut self, segment: &str, schema: &OwnedNamedType) -> Result<Value, InspectError> {
+        let len = self.path.len();
+        self.path.push_str(segment);
+        let value = self.value(schema)?;
+        self.path.truncate(len);
+        Ok(value)
+    }
+
+    fn value(&mut self, schema: &OwnedNamedType) -> Result<Value, InspectError> {
+        Ok(match &schema.ty {
+            OwnedDataModelType::Bool => Value::Bool(self.take()?),
+            OwnedDataModelType::I8 => Value::Int(self.take::<i8>()?.into()),
+            OwnedDataModelType::I16 => Value::Int(self.take::<i16>()?.into()),
+            OwnedDataModelType::I32 => Value::Int(self.take::<i32>()?.into()),
+            OwnedDataModelType::I64 | OwnedDataModelType::Isize => {
+                Value::Int(self.take::<i64>()?.into())
+            }
+            OwnedDataModelType::I128 => Value::Int(self.take()?),
+            OwnedDataModelType::U8 => Value::Uint(self.take::<u8>()?.into()),
+            OwnedDataModelType::U16 => Value::Uint(self.take::<u16>()?.into()),
+            OwnedDataModelType::U32 => Value::Uint(self.take::<u32>()?.into()),
+            OwnedDataModelType::U64 | OwnedDataModelType::Usize => {
+                Value::Uint(self.take::<u64>()?.into())
+            }
+            OwnedDataModelType::U128 => Value::Uint(self.take()?),
+            OwnedDataModelType::F32 => Value::F32(self.take()?),
+            OwnedDataModelType::F64 => Value::F64(self.take()?),
+            OwnedDataModelType::Char => Value::Char(self.take()?),
+            OwnedDataModelType::String => Value::String(self.take::<&str>()?.to_owned()),
+            OwnedDataModelType::ByteArray => Value::Bytes(self.take::<&[u8]>()?.to_vec()),
+            OwnedDataModelType::Option(inner) => match self.take::<u8>()? {
+                0 => Value::Option(None),
+                1 => Value::Option(Some(Box::new(self.value(inner)?))),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error(postcard::Error::DeserializeBadOption));
+                }
+            },
+            OwnedDataModelType::Unit => Value::Unit,
+            OwnedDataModelType::UnitStruct => Value::Struct(schema.name.clone(), Fields::Unit),
+            OwnedDataModelType::NewtypeStruct(inner) => {
+                Value::Struct(schema.name.clone(), Fields::Tuple(self.tuple(&[inner])?))
+            }
+            OwnedDataModelType::Seq(inner) => {
+                let len = self.len()?;
+                let values = (0..len)
+                    .map(|i| self.nested(&format!("[{i}]"), inner))
+                    .collect::<Result<_, _>>()?;
+                Value::Seq(values)
+            }
+            OwnedDataModelType::Tuple(elements) => {
+                Value::Tuple(self.tuple(&elements.iter().collect::<Vec<_>>())?)
+            }
+            OwnedDataModelType::TupleStruct(elements) => Value::Struct(
+                schema.name.clone(),
+                Fields::Tuple(self.tuple(&elements.iter().collect::<Vec<_>>())?),
+            ),
+            OwnedDataModelType::Map { key, val } => {
+                let len = self.len()?;
+                let entries = (0..len)
+                    .map(|i| {
+                        let k = self.nested(&format!("[{i}].key"), key)?;
+                        let v = self.nested(&format!("[{k:?}]"), val)?;
+                        Ok((k, v))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Value::Map(entries)
+            }
+            OwnedDataModelType::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|f| Ok((f.name.clone(), self.nested(&format!(".{}", f.name), &f.ty)?)))
+                    .collect::<Result<_, _>>()?;
+                Value::Struct(schema.name.clone(), Fields::Named(fields))
+            }
+            OwnedDataModelType::Enum(variants) => {
+                let start = self.position;
+                let index = self.take::<u32>()?;
+                let Some(variant) = variants.get(index as usize) else {
+                    self.position = start;
+                    return Err(self.error(postcard::Error::DeserializeBadEnum));
+                };
+                let len = self.path.len();
+                self.path.push_str(&format!("::{}", variant.name));
+                let fields = match &variant.ty {
+                    OwnedDataModelVariant::UnitVariant => Fields::Unit,
+                    OwnedDataModelVariant::NewtypeVariant(inner) => {
+                        Fields::Tuple(self.tuple(&[inner])?)
+                    }
+                    OwnedDataModelVariant::TupleVariant(elements) => {
+                        Fields::Tuple(self.tuple(&elements.iter().collect::<Vec<_>>())?)
+                    }
+                    OwnedDataModelVariant::StructVariant(fields) => Fields::Named(
+                        fields
+                            .iter()
+                            .map(|f| {
+                                Ok((f.name.clone(), self.nested(&format!(".{}", f.name), &f.ty)?))
+                            })
+                            .collect::<Result<_, _>>()?,
+                    ),
+                };
+                self.path.truncate(len);
+                Value::Variant(schema.name.clone(), variant.name.clone(), fields)
+            }
+            OwnedDataModelType::Schema => {
+                return Err(self.error(postcard::Error::NotYetImplemented))
+            }
+        })
+    }
+
+    fn tuple(&mut self, elements: &[&OwnedNamedType]) -> Result<Vec<Value>, InspectError> {
+        elements
+            .iter()
+            .enumerate()
+            .map(|(i, e)| self.nested(&format!(".{i}"), e))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::format;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use a653rs::prelude::QueuingDiscipline;
+    use mock::MockHyp;
+    use serde::Serialize;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    // Only used as hypervisor type
+    #[allow(dead_code, clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Serialize, Schema)]
+    enum Command {
+        Stop,
+        Move {
+            speed: Option<u16>,
+            target: (i8, i8),
+        },
+        Log(String),
+    }
+
+    #[derive(Serialize, Schema)]
+    struct Frame {
+        id: u8,
+        commands: Vec<Command>,
+        limits: BTreeMap<String, f32>,
+    }
+
+    #[test]
+    fn inspect_message() {
+        let frame = Frame {
+            id: 7,
+            commands: std::vec![
+                Command::Stop,
+                Command::Move {
+                    speed: Some(300),
+                    target: (-1, 2),
+                },
+                Command::Log(String::from("ok")),
+            ],
+            limits: BTreeMap::from([(String::from("max"), 0.1)]),
+        };
+        let schema = OwnedNamedType::from(Frame::SCHEMA);
+        let bytes = postcard::to_allocvec(&frame).unwrap();
+
+        let (value, rest) = inspect(&schema, &bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            r#"Frame { id: 7, commands: [Command::Stop, Command::Move { speed: Some(300), target: (-1, 2) }, Command::Log("ok")], limits: {"max": 0.1} }"#,
+            format!("{value:?}")
+        );
+        assert_eq!(serde_json::to_value(&frame).unwrap(), value.to_json());
+
+        // Unknown variant of the second command
+        let mut corrupted = bytes.clone();
+        corrupted[3] = 9;
+        let err = inspect(&schema, &corrupted).unwrap_err();
+        assert_eq!(postcard::Error::DeserializeBadEnum, err.error);
+        assert_eq!((3, ".commands[1]"), (err.position, err.path.as_str()));
+
+        let err = inspect(&schema, &bytes[..6]).unwrap_err();
+        assert_eq!(postcard::Error::DeserializeUnexpectedEnd, err.error);
+        assert_eq!(
+            (5, ".commands[1]::Move.speed"),
+            (err.position, err.path.as_str())
+        );
+    }
+
+    #[test]
+    fn inspect_icd_schema() {
+        let icd = Icd::new([
+            PortDescription::sampling::<TypedSamplingPortSource<u16, MockHyp>>("HK", None),
+            PortDescription::queuing::<TypedQueuingPortReceiver<Option<char>, MockHyp>>(
+                "KEYS",
+                4,
+                QueuingDiscipline::Fifo,
+            ),
+        ]);
+        let json = icd.to_json().unwrap();
+
+        let schema = icd_schema(&json, "KEYS").unwrap().unwrap();
+        assert_eq!(OwnedNamedType::from(<Option<char>>::SCHEMA), schema);
+        let (value, rest) = inspect(&schema, &[1, 1, b'a', 0xFF]).unwrap();
+        assert_eq!(
+            Value::Option(Some(std::boxed::Box::new(Value::Char('a')))),
+            value
+        );
+        assert_eq!([0xFF], rest);
+
+        assert_eq!(None, icd_schema(&json, "UNKNOWN").unwrap());
+        assert!(icd_schema("{", "HK").is_err());
+    }
+}
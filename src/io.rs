@@ -0,0 +1,464 @@
+//! [`embedded_io`] and [`std::io`] byte streams over queuing ports
+//!
+//! Bytes written to a [`QueuingPortWriter`] are packed into messages of up to the port size.
+//! A [`QueuingPortReader`] returns the bytes of received messages in order,
+//! regardless of message boundaries.
+//! Together with [`postcard::to_eio`] and [`postcard::from_eio`],
+//! or [`postcard::to_io`] and [`postcard::from_io`] with the `std` feature,
+//! values can be streamed across partition boundaries.
+
+#[cfg(feature = "std")]
+extern crate std;
+
+use a653rs::prelude::*;
+
+use crate::error::*;
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for IoError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        use embedded_io::ErrorKind;
+        match self {
+            IoError::Apex(Error::TimedOut) => ErrorKind::TimedOut,
+            IoError::Apex(Error::InvalidParam) => ErrorKind::InvalidInput,
+            IoError::Apex(Error::InvalidConfig) => ErrorKind::InvalidInput,
+            IoError::Apex(Error::InvalidMode) => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<IoError> for std::io::Error {
+    fn from(e: IoError) -> Self {
+        use std::io::ErrorKind;
+        let kind = match e {
+            IoError::Apex(Error::TimedOut) => ErrorKind::TimedOut,
+            IoError::Apex(Error::InvalidParam) => ErrorKind::InvalidInput,
+            IoError::Apex(Error::InvalidConfig) => ErrorKind::InvalidInput,
+            IoError::Apex(Error::InvalidMode) => ErrorKind::Unsupported,
+            _ => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, std::format!("{e:?}"))
+    }
+}
+
+/// Queuing port sender accepting a byte stream
+///
+/// Written bytes are buffered until the buffer holds the port size
+/// and further bytes are written, or the writer is flushed.
+/// Errors of sending a message are therefore returned by the next write or flush,
+/// which retries sending the buffered bytes.
+/// Buffered bytes are discarded when the writer is dropped without flushing.
+///
+/// `N` is the size of the internal message buffer.
+/// It must be at least the size of the port.
+#[derive(Debug)]
+pub struct QueuingPortWriter<const N: usize, Q: ApexQueuingPortP4Ext> {
+    port: QueuingPortSender<Q>,
+    timeout: SystemTime,
+    pending: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize, Q: ApexQueuingPortP4Ext> QueuingPortWriter<N, Q> {
+    /// Write a byte stream to `port`, waiting indefinitely for free queue space
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the port size exceeds `N`.
+    pub fn new(port: QueuingPortSender<Q>) -> Result<Self, Error> {
+        if port.size() > N {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(Self {
+            port,
+            timeout: SystemTime::Infinite,
+            pending: [0; N],
+            len: 0,
+        })
+    }
+
+    /// Wait up to `timeout` for free queue space when sending a message
+    pub fn with_timeout(mut self, timeout: SystemTime) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortSender<Q> {
+        &self.port
+    }
+
+    /// Consume the writer, returning the underlying queuing port
+    ///
+    /// Buffered bytes are discarded.
+    pub fn into_inner(self) -> QueuingPortSender<Q> {
+        self.port
+    }
+
+    fn send_pending(&mut self) -> Result<(), IoError> {
+        if self.len > 0 {
+            self.port
+                .send(&self.pending[..self.len], self.timeout.clone())?;
+            self.len = 0;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        let size = self.port.size();
+        if self.len == size {
+            self.send_pending()?;
+        }
+        let count = buf.len().min(size - self.len);
+        self.pending[self.len..self.len + count].copy_from_slice(&buf[..count]);
+        self.len += count;
+        Ok(count)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> embedded_io::ErrorType for QueuingPortWriter<N, Q> {
+    type Error = IoError;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> embedded_io::Write for QueuingPortWriter<N, Q> {
+    /// Buffer bytes of `buf`, first sending a message if the buffer holds the port size
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// use embedded_io::Write;
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let writer = QueuingPortWriter::<16, _>::new(port).unwrap();
+    /// let mut writer = postcard::to_eio("Larger than a single message", writer).unwrap();
+    /// writer.flush().unwrap();
+    /// # })
+    /// ```
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.write_bytes(buf)
+    }
+
+    /// Send buffered bytes as a message
+    fn flush(&mut self) -> Result<(), IoError> {
+        self.send_pending()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> std::io::Write for QueuingPortWriter<N, Q> {
+    /// Buffer bytes of `buf`, first sending a message if the buffer holds the port size
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// use std::io::Write;
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let writer = QueuingPortWriter::<16, _>::new(port).unwrap();
+    /// let mut writer = postcard::to_io("Larger than a single message", writer).unwrap();
+    /// writer.flush().unwrap();
+    /// # })
+    /// ```
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(self.write_bytes(buf)?)
+    }
+
+    /// Send buffered bytes as a message
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(self.send_pending()?)
+    }
+}
+
+/// Queuing port receiver providing a byte stream
+///
+/// Remaining bytes of a received message are returned by subsequent reads.
+/// Empty messages are skipped.
+///
+/// `N` is the size of the internal message buffer.
+/// It must be at least the size of the port.
+#[derive(Debug)]
+pub struct QueuingPortReader<const N: usize, Q: ApexQueuingPortP4Ext> {
+    port: QueuingPortReceiver<Q>,
+    timeout: SystemTime,
+    overflow: QueueOverflow,
+    pending: [u8; N],
+    start: usize,
+    end: usize,
+}
+
+impl<const N: usize, Q: ApexQueuingPortP4Ext> QueuingPortReader<N, Q> {
+    /// Read a byte stream from `port`, waiting indefinitely for messages
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the port size exceeds `N`.
+    pub fn new(port: QueuingPortReceiver<Q>) -> Result<Self, Error> {
+        if port.size() > N {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(Self {
+            port,
+            timeout: SystemTime::Infinite,
+            overflow: false,
+            pending: [0; N],
+            start: 0,
+            end: 0,
+        })
+    }
+
+    /// Wait up to `timeout` for a message when no buffered bytes are left
+    pub fn with_timeout(mut self, timeout: SystemTime) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the queue overflowed since the last call, so that bytes may be missing
+    pub fn take_overflow(&mut self) -> QueueOverflow {
+        core::mem::take(&mut self.overflow)
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortReceiver<Q> {
+        &self.port
+    }
+
+    /// Consume the reader, returning the underlying queuing port
+    ///
+    /// Buffered bytes are discarded.
+    pub fn into_inner(self) -> QueuingPortReceiver<Q> {
+        self.port
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.start == self.end {
+            let (msg, overflow) = self.port.receive(&mut self.pending, self.timeout.clone())?;
+            self.overflow |= overflow;
+            self.start = 0;
+            self.end = msg.len();
+        }
+        let count = buf.len().min(self.end - self.start);
+        buf[..count].copy_from_slice(&self.pending[self.start..self.start + count]);
+        self.start += count;
+        Ok(count)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> embedded_io::ErrorType for QueuingPortReader<N, Q> {
+    type Error = IoError;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> embedded_io::Read for QueuingPortReader<N, Q> {
+    /// Read buffered bytes, receiving a message if none are left
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # use embedded_io::Write;
+    /// # let writer = QueuingPortWriter::<16, _>::new(src_port).unwrap();
+    /// # postcard::to_eio("Larger than a single message", writer).unwrap().flush().unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let reader = QueuingPortReader::<16, _>::new(port).unwrap();
+    /// let mut buf = [0; 500];
+    /// let (msg, _) = postcard::from_eio::<&str, _>((reader, &mut buf)).unwrap();
+    /// assert_eq!("Larger than a single message", msg);
+    /// # })
+    /// ```
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.read_bytes(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize, Q: ApexQueuingPortP4Ext> std::io::Read for QueuingPortReader<N, Q> {
+    /// Read buffered bytes, receiving a message if none are left
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # use std::io::Write;
+    /// # let writer = QueuingPortWriter::<16, _>::new(src_port).unwrap();
+    /// # postcard::to_io("Larger than a single message", writer).unwrap().flush().unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let reader = QueuingPortReader::<16, _>::new(port).unwrap();
+    /// let mut buf = [0; 500];
+    /// let (msg, _) = postcard::from_io::<String, _>((reader, &mut buf)).unwrap();
+    /// assert_eq!("Larger than a single message", msg);
+    /// # })
+    /// ```
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.read_bytes(buf)?)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn io_stream() {
+        use a653rs::prelude::Error;
+        use embedded_io::{Read, Write};
+
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut writer = QueuingPortWriter::<4, _>::new(src_port).unwrap();
+            let mut reader = QueuingPortReader::<4, _>::new(dest_port)
+                .unwrap()
+                .with_timeout(SystemTime::Normal(Duration::ZERO));
+
+            writer.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+            let mut buf = [0; 3];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!([1, 2, 3], buf);
+            assert_eq!(1, reader.read(&mut buf).unwrap());
+            assert_eq!(4, buf[0]);
+            // The remaining bytes were not flushed yet
+            assert!(matches!(
+                reader.read(&mut buf),
+                Err(IoError::Apex(Error::NotAvailable))
+            ));
+            writer.flush().unwrap();
+            assert_eq!(2, reader.read(&mut buf).unwrap());
+            assert_eq!([5, 6], buf[..2]);
+            assert!(!reader.take_overflow());
+
+            let mut writer = postcard::to_eio(&(42u32, "Typed Data"), writer).unwrap();
+            writer.flush().unwrap();
+            let mut buf = [0; 100];
+            let ((num, msg), _) = postcard::from_eio::<(u32, &str), _>((reader, &mut buf)).unwrap();
+            assert_eq!(42, num);
+            assert_eq!("Typed Data", msg);
+        })
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_std_stream() {
+        use std::io::{ErrorKind, Read, Write};
+        use std::string::String;
+
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut writer = QueuingPortWriter::<4, _>::new(src_port).unwrap();
+            let mut reader = QueuingPortReader::<4, _>::new(dest_port)
+                .unwrap()
+                .with_timeout(SystemTime::Normal(Duration::ZERO));
+
+            // A full buffer is only sent by the next write or flush
+            assert_eq!(4, writer.write(&[1, 2, 3, 4, 5]).unwrap());
+            let mut buf = [0; 4];
+            assert_eq!(ErrorKind::Other, reader.read(&mut buf).unwrap_err().kind());
+            assert_eq!(1, writer.write(&[5]).unwrap());
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!([1, 2, 3, 4], buf);
+            writer.flush().unwrap();
+            assert_eq!(1, reader.read(&mut buf).unwrap());
+            assert_eq!(5, buf[0]);
+
+            let mut writer = postcard::to_io(&(42u32, "Typed Data"), writer).unwrap();
+            writer.flush().unwrap();
+            let mut buf = [0; 100];
+            let ((num, msg), _) =
+                postcard::from_io::<(u32, String), _>((reader, &mut buf)).unwrap();
+            assert_eq!(42, num);
+            assert_eq!("Typed Data", msg);
+        })
+    }
+}
@@ -1,7 +1,40 @@
 #![no_std]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+// Allows code generated by the derive macros to refer to this crate by name
+#[cfg(feature = "derive")]
+extern crate self as a653rs_postcard;
+
+pub mod batch;
+pub mod cache;
+pub mod ccsds;
+pub mod cobs;
+pub mod codec;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod error;
+pub mod fan_out;
+pub mod forward;
+#[cfg(feature = "icd")]
+pub mod icd;
+#[cfg(feature = "icd")]
+pub mod inspect;
+#[cfg(any(feature = "embedded-io", feature = "std"))]
+pub mod io;
+pub mod limits;
+pub mod on_change;
+#[cfg(feature = "bytemuck")]
+pub mod pod;
+#[cfg(feature = "derive")]
+pub mod ports;
 pub mod prelude;
 pub mod queuing;
+#[cfg(feature = "record")]
+pub mod record;
 pub mod sampling;
+pub mod select;
+#[cfg(feature = "std")]
+pub mod udp;
+pub mod validate;
+pub mod version;
+pub mod voting;
@@ -0,0 +1,578 @@
+//! Bounded-resource decoding of untrusted messages
+//!
+//! [`Limits`] is a [`Codec`] using postcard's wire format,
+//! which rejects messages exceeding configured sequence lengths, string lengths,
+//! nesting depth or allocation sizes while deserializing.
+//! Every receiver may use its own limits with the `_with` variants of the extension traits,
+//! which report exceeded limits in the `Limit` variants of their errors.
+//! Typed ports apply their limits to every received value,
+//! e.g. when declared with `#[queuing(..., codec = LIMITS)]` in [`PartitionPorts`](crate::ports::PartitionPorts).
+
+use core::cell::Cell;
+use core::fmt;
+use core::mem::size_of;
+
+use postcard::de_flavors::Slice as DeSlice;
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::codec::{Codec, Decode, Encode, Postcard};
+use crate::error::{DecodeFailure, LimitError};
+
+/// Postcard codec enforcing decode limits
+///
+/// All limits are unlimited by default.
+/// Allocations are estimated from the number of sequence and map elements times their size,
+/// and the lengths of strings and byte arrays.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// # use std::time::Duration;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// # Hypervisor::run_test(|mut ctx| {
+/// # let src_port = ctx
+/// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// # let port = ctx
+/// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// # let mut buf = [0; 500];
+/// # src_port.send_type_buf(vec![String::from("Command"); 20], SystemTime::Infinite, &mut buf).unwrap();
+///
+/// let port: QueuingPortReceiver<Hypervisor> = port;
+/// let limits = Limits {
+///     max_seq_len: 16,
+///     max_str_len: 64,
+///     ..Limits::default()
+/// };
+/// let received = port.recv_type_buf_with::<_, Vec<String>>(&limits, SystemTime::Infinite, &mut buf);
+/// assert!(matches!(
+///     received,
+///     Err(QueuingRecvBufError::Limit(LimitError::SequenceLength(20), _))
+/// ));
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of elements of a sequence or map
+    pub max_seq_len: usize,
+    /// Maximum length of a string or byte array in bytes
+    pub max_str_len: usize,
+    /// Maximum nesting depth of sequences, maps, options, newtypes and enums
+    pub max_depth: usize,
+    /// Maximum estimated total allocation in bytes
+    pub max_alloc: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_seq_len: usize::MAX,
+            max_str_len: usize::MAX,
+            max_depth: usize::MAX,
+            max_alloc: usize::MAX,
+        }
+    }
+}
+
+/// Exceeded limits are reported in the `Limit` variants of the receive errors
+impl Codec for Limits {
+    type Error = LimitError;
+
+    fn failure(e: LimitError) -> DecodeFailure<LimitError> {
+        match e {
+            LimitError::Postcard(_) => DecodeFailure::Codec(e),
+            e => DecodeFailure::Limit(e),
+        }
+    }
+}
+
+/// Values are encoded using [`Postcard`] without any limits
+impl<T: Serialize + ?Sized> Encode<T> for Limits {
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], LimitError> {
+        Postcard.encode(value, buf).map_err(LimitError::Postcard)
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> Decode<T> for Limits {
+    fn decode(&self, msg: &[u8]) -> Result<T, LimitError> {
+        let tracker = Tracker {
+            limits: self,
+            depth: Cell::new(0),
+            alloc: Cell::new(0),
+            exceeded: Cell::new(None),
+        };
+        let mut deserializer = postcard::Deserializer::from_flavor(DeSlice::new(msg));
+        let de = Limited {
+            inner: &mut deserializer,
+            tracker: &tracker,
+            inline: false,
+        };
+        T::deserialize(de).map_err(|e| tracker.exceeded.take().unwrap_or(LimitError::Postcard(e)))
+    }
+}
+
+/// Resource usage while deserializing a single message
+struct Tracker<'l> {
+    limits: &'l Limits,
+    depth: Cell<usize>,
+    alloc: Cell<usize>,
+    exceeded: Cell<Option<LimitError>>,
+}
+
+impl Tracker<'_> {
+    /// Record the exceeded limit, returning an error aborting deserialization
+    fn fail<E: de::Error>(&self, e: LimitError) -> E {
+        let msg = match e {
+            LimitError::SequenceLength(_) => "sequence too long",
+            LimitError::StringLength(_) => "string too long",
+            LimitError::Depth => "nesting too deep",
+            LimitError::Allocation(_) => "allocation too large",
+            LimitError::Postcard(_) => "postcard error",
+        };
+        self.exceeded.set(Some(e));
+        E::custom(msg)
+    }
+
+    fn check_seq<E: de::Error>(&self, len: usize) -> Result<(), E> {
+        if len > self.limits.max_seq_len {
+            return Err(self.fail(LimitError::SequenceLength(len)));
+        }
+        Ok(())
+    }
+
+    fn check_str<E: de::Error>(&self, len: usize) -> Result<(), E> {
+        if len > self.limits.max_str_len {
+            return Err(self.fail(LimitError::StringLength(len)));
+        }
+        self.allocate(len)
+    }
+
+    fn allocate<E: de::Error>(&self, size: usize) -> Result<(), E> {
+        let alloc = self.alloc.get().saturating_add(size);
+        if alloc > self.limits.max_alloc {
+            return Err(self.fail(LimitError::Allocation(alloc)));
+        }
+        self.alloc.set(alloc);
+        Ok(())
+    }
+
+    /// Run `f` one nesting level deeper
+    fn nested<T, E: de::Error>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let depth = self.depth.get() + 1;
+        if depth > self.limits.max_depth {
+            return Err(self.fail(LimitError::Depth));
+        }
+        self.depth.set(depth);
+        let result = f();
+        self.depth.set(depth - 1);
+        result
+    }
+}
+
+/// Deserializer checking limits before passing data to the visitors of `inner`
+struct Limited<'t, 'l, D> {
+    inner: D,
+    tracker: &'t Tracker<'l>,
+    /// Visited sequences are tuples or structs stored inline, not allocated
+    inline: bool,
+}
+
+impl<'t, 'l, D> Limited<'t, 'l, D> {
+    fn wrap<V>(&self, visitor: V) -> Limited<'t, 'l, V> {
+        Limited {
+            inner: visitor,
+            tracker: self.tracker,
+            inline: false,
+        }
+    }
+
+    fn wrap_inline<V>(&self, visitor: V) -> Limited<'t, 'l, V> {
+        Limited {
+            inline: true,
+            ..self.wrap(visitor)
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($wrap:ident: $($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error> {
+                let visitor = self.$wrap(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Limited<'_, '_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        wrap:
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_map(),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    forward_deserialize! {
+        wrap_inline:
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for Limited<'_, '_, T> {
+    type Value = T::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T::Value, D::Error> {
+        let de = self.wrap(deserializer);
+        self.inner.deserialize(de)
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Limited<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        self.tracker.check_str(v.len())?;
+        self.inner.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<V::Value, E> {
+        self.tracker.check_str(v.len())?;
+        self.inner.visit_borrowed_str(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<V::Value, E> {
+        self.tracker.check_str(v.len())?;
+        self.inner.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<V::Value, E> {
+        self.tracker.check_str(v.len())?;
+        self.inner.visit_borrowed_bytes(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        let de = self.wrap(deserializer);
+        self.tracker.nested(|| self.inner.visit_some(de))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error> {
+        let de = self.wrap(deserializer);
+        self.tracker.nested(|| self.inner.visit_newtype_struct(de))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        if let (false, Some(len)) = (self.inline, seq.size_hint()) {
+            self.tracker.check_seq(len)?;
+        }
+        let seq = LimitedAccess {
+            inner: seq,
+            tracker: self.tracker,
+            inline: self.inline,
+            count: 0,
+        };
+        self.tracker.nested(|| self.inner.visit_seq(seq))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        if let (false, Some(len)) = (self.inline, map.size_hint()) {
+            self.tracker.check_seq(len)?;
+        }
+        let map = LimitedAccess {
+            inner: map,
+            tracker: self.tracker,
+            inline: self.inline,
+            count: 0,
+        };
+        self.tracker.nested(|| self.inner.visit_map(map))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        let data = self.wrap(data);
+        self.tracker.nested(|| self.inner.visit_enum(data))
+    }
+}
+
+/// Sequence or map access counting and accounting for its elements
+struct LimitedAccess<'t, 'l, A> {
+    inner: A,
+    tracker: &'t Tracker<'l>,
+    inline: bool,
+    count: usize,
+}
+
+impl<A> LimitedAccess<'_, '_, A> {
+    fn next<E: de::Error>(&mut self, size: usize) -> Result<(), E> {
+        if self.inline {
+            return Ok(());
+        }
+        self.count += 1;
+        self.tracker.check_seq(self.count)?;
+        self.tracker.allocate(size)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for LimitedAccess<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        self.next(size_of::<T::Value>())?;
+        let seed = Limited {
+            inner: seed,
+            tracker: self.tracker,
+            inline: false,
+        };
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for LimitedAccess<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        self.next(size_of::<K::Value>())?;
+        let seed = Limited {
+            inner: seed,
+            tracker: self.tracker,
+            inline: false,
+        };
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        if !self.inline {
+            self.tracker.allocate(size_of::<V::Value>())?;
+        }
+        let seed = Limited {
+            inner: seed,
+            tracker: self.tracker,
+            inline: false,
+        };
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 't, 'l, A: EnumAccess<'de>> EnumAccess<'de> for Limited<'t, 'l, A> {
+    type Error = A::Error;
+    type Variant = Limited<'t, 'l, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), A::Error> {
+        let seed = self.wrap(seed);
+        let tracker = self.tracker;
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        let variant = Limited {
+            inner: variant,
+            tracker,
+            inline: false,
+        };
+        Ok((value, variant))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Limited<'_, '_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap_inline(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap_inline(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::string::String;
+    use std::vec;
+    use std::vec::Vec;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Tree {
+        Leaf(u8),
+        Node(Vec<Tree>),
+    }
+
+    fn roundtrip<T>(limits: &Limits, value: &T) -> Result<T, LimitError>
+    where
+        T: Serialize + for<'a> Deserialize<'a>,
+    {
+        let mut buf = [0; 1000];
+        let msg = postcard::to_slice(value, &mut buf).unwrap();
+        limits.decode(msg)
+    }
+
+    #[test]
+    fn limits_unlimited() {
+        let tree = Tree::Node(vec![Tree::Leaf(1), Tree::Node(vec![Tree::Leaf(2)])]);
+        assert_eq!(tree, roundtrip(&Limits::default(), &tree).unwrap());
+        let map = BTreeMap::from([(1u8, String::from("One")), (2, String::from("Two"))]);
+        assert_eq!(map, roundtrip(&Limits::default(), &map).unwrap());
+    }
+
+    #[test]
+    fn limits_exceeded() {
+        let limits = Limits {
+            max_seq_len: 3,
+            max_str_len: 4,
+            max_depth: 4,
+            max_alloc: 100,
+        };
+        assert!(matches!(
+            roundtrip(&limits, &vec![0u8; 4]),
+            Err(LimitError::SequenceLength(4))
+        ));
+        assert!(matches!(
+            roundtrip(&limits, &String::from("Hello")),
+            Err(LimitError::StringLength(5))
+        ));
+        assert!(matches!(
+            roundtrip(&limits, &vec![[0u64; 8]; 2]),
+            Err(LimitError::Allocation(128))
+        ));
+        // Every tree level nests an enum and a sequence
+        let deep = Tree::Node(vec![Tree::Node(vec![Tree::Leaf(0)])]);
+        assert!(matches!(roundtrip(&limits, &deep), Err(LimitError::Depth)));
+        let shallow = Tree::Node(vec![Tree::Leaf(0)]);
+        assert_eq!(shallow, roundtrip(&limits, &shallow).unwrap());
+        assert!(matches!(
+            limits.decode(&[0xFF]) as Result<u32, _>,
+            Err(LimitError::Postcard(_))
+        ));
+    }
+
+    #[test]
+    fn limits_corrupted_length() {
+        // Sequence length of `u32::MAX` with only a few elements following,
+        // so that postcard does not provide the length and elements are counted instead
+        let msg = [0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 1, 2, 3, 4, 5, 6];
+        let limits = Limits {
+            max_seq_len: 4,
+            ..Limits::default()
+        };
+        let decoded: Result<Vec<u8>, _> = limits.decode(&msg);
+        assert!(matches!(decoded, Err(LimitError::SequenceLength(5))));
+    }
+}
@@ -0,0 +1,205 @@
+//! Change-only publication on sampling port sources
+
+use core::time::Duration;
+
+use a653rs::prelude::*;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::Serialize;
+
+use crate::error::*;
+
+/// Sampling port source which only writes messages that differ from the previous one
+///
+/// The serialized message is compared with the last message written to the port.
+/// Unchanged messages are not written, saving a call to the hypervisor.
+/// With a refresh interval, unchanged messages are still rewritten once the interval
+/// has passed, so that the [`Validity`] of the destination does not expire.
+///
+/// `N` is the maximum message size which can be remembered.
+/// It must be at least the size of the port.
+#[derive(Debug)]
+pub struct OnChangeSamplingPortSource<const N: usize, S: ApexSamplingPortP4Ext + ApexTimeP4Ext> {
+    port: SamplingPortSource<S>,
+    refresh: Option<Duration>,
+    last: [u8; N],
+    last_len: Option<usize>,
+    last_sent: Duration,
+}
+
+impl<const N: usize, S: ApexSamplingPortP4Ext + ApexTimeP4Ext> OnChangeSamplingPortSource<N, S> {
+    /// Wrap `port`, only writing changed messages
+    ///
+    /// Fails with [`Error::InvalidConfig`] if the port size exceeds `N`.
+    pub fn new(port: SamplingPortSource<S>) -> Result<Self, Error> {
+        if port.size() as usize > N {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(Self {
+            port,
+            refresh: None,
+            last: [0; N],
+            last_len: None,
+            last_sent: Duration::ZERO,
+        })
+    }
+
+    /// Rewrite unchanged messages after `refresh` has passed since the last write
+    pub fn with_refresh(mut self, refresh: Duration) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// Send a type if it differs from the previously written one
+    ///
+    /// Requires a buffer `buf` for serialization.
+    /// Returns whether the message was written to the port.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortSource<Hypervisor> = port;
+    /// let mut port = OnChangeSamplingPortSource::<500, _>::new(port)
+    ///     .unwrap()
+    ///     .with_refresh(Duration::from_millis(100));
+    /// let mut buf = [0; 500];
+    /// assert!(port.send_type_buf(42u32, &mut buf).unwrap());
+    /// assert!(!port.send_type_buf(42u32, &mut buf).unwrap());
+    /// # })
+    /// ```
+    pub fn send_type_buf<T>(&mut self, p: T, buf: &mut [u8]) -> Result<bool, SendError>
+    where
+        T: Serialize,
+    {
+        let buf =
+            postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(&p, SerSlice::new(buf))?;
+        self.publish(buf)
+    }
+
+    /// Send a type if it differs from the previously written one
+    ///
+    /// Returns whether the message was written to the port.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortSource<Hypervisor> = port;
+    /// let mut port = OnChangeSamplingPortSource::<500, _>::new(port).unwrap();
+    /// port.send_type(String::from("Typed Data")).unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn send_type<T>(&mut self, p: T) -> Result<bool, SendError>
+    where
+        T: Serialize,
+    {
+        let msg = postcard::to_allocvec(&p)?;
+        self.publish(&msg)
+    }
+
+    /// Write the next message regardless of whether it changed
+    pub fn force_next(&mut self) {
+        self.last_len = None;
+    }
+
+    /// Underlying sampling port
+    pub fn port(&self) -> &SamplingPortSource<S> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying sampling port
+    pub fn into_inner(self) -> SamplingPortSource<S> {
+        self.port
+    }
+
+    fn publish(&mut self, msg: &[u8]) -> Result<bool, SendError> {
+        let now = match <S as ApexTimeP4Ext>::get_time() {
+            SystemTime::Normal(now) => now,
+            SystemTime::Infinite => Duration::MAX,
+        };
+        let unchanged = self.last_len.is_some_and(|len| self.last[..len] == *msg);
+        let expired = self
+            .refresh
+            .is_some_and(|refresh| now.saturating_sub(self.last_sent) >= refresh);
+        if unchanged && !expired {
+            return Ok(false);
+        }
+
+        self.port.send(msg)?;
+        self.last[..msg.len()].copy_from_slice(msg);
+        self.last_len = Some(msg.len());
+        self.last_sent = now;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::prelude::Name;
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn on_change_refresh() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+                .unwrap();
+            let mut src_port = OnChangeSamplingPortSource::<500, _>::new(src_port)
+                .unwrap()
+                .with_refresh(Duration::from_secs(1));
+            let mut buf = [0; 500];
+
+            assert!(src_port.send_type_buf(1u32, &mut buf).unwrap());
+            assert!(!src_port.send_type_buf(1u32, &mut buf).unwrap());
+            assert!(src_port.send_type_buf(2u32, &mut buf).unwrap());
+            MockHyp::advance(Duration::from_millis(500));
+            assert!(!src_port.send_type_buf(2u32, &mut buf).unwrap());
+            MockHyp::advance(Duration::from_millis(500));
+            assert!(src_port.send_type_buf(2u32, &mut buf).unwrap());
+            src_port.force_next();
+            assert!(src_port.send_type_buf(2u32, &mut buf).unwrap());
+        })
+    }
+
+    #[test]
+    fn on_change_port_too_large() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+                .unwrap();
+            assert!(OnChangeSamplingPortSource::<100, _>::new(src_port).is_err());
+        })
+    }
+}
@@ -0,0 +1,187 @@
+//! Fixed-layout codec for plain-old-data types
+//!
+//! Messages are the raw bytes of `#[repr(C)]` types implementing the [`bytemuck`] traits.
+//! Unlike postcard's variable-length encoding,
+//! message size and decoding time only depend on the type, never on the value.
+
+use core::mem::size_of;
+
+use bytemuck::{CheckedBitPattern, NoUninit};
+
+use crate::codec::{Codec, Decode, Encode};
+use crate::error::PodError;
+
+/// Codec sending the raw bytes of plain-old-data types
+///
+/// Types are encoded in the native byte order and layout,
+/// so sender and receiver need to share both.
+/// Received messages must have exactly the size of the type.
+/// They are copied before being interpreted, so the alignment of the receive buffer does not matter.
+/// Types with invalid bit patterns, like `bool` or fieldless enums, are validated on receive.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// # use std::time::Duration;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// # Hypervisor::run_test(|mut ctx| {
+/// # let src_port = ctx
+/// #     .create_sampling_port_source(Name::from_str("").unwrap(), 12)
+/// #     .unwrap();
+/// # let port = ctx
+/// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 12, Duration::ZERO)
+/// #     .unwrap();
+/// use bytemuck::{Pod, Zeroable};
+///
+/// #[derive(Clone, Copy, Pod, Zeroable)]
+/// #[repr(C)]
+/// struct Attitude {
+///     roll: f32,
+///     pitch: f32,
+///     yaw: f32,
+/// }
+///
+/// let src_port: SamplingPortSource<Hypervisor> = src_port;
+/// let port: SamplingPortDestination<Hypervisor> = port;
+/// let mut buf = [0; 12];
+/// let attitude = Attitude { roll: 0.1, pitch: 0.2, yaw: 0.3 };
+/// src_port.send_type_buf_with(&PodCodec, attitude, &mut buf).unwrap();
+/// let (_, attitude): (_, Attitude) = port.recv_type_buf_with(&PodCodec, &mut buf).unwrap();
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PodCodec;
+
+impl Codec for PodCodec {
+    type Error = PodError;
+}
+
+impl<T: NoUninit> Encode<T> for PodCodec {
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], PodError> {
+        let buf = buf.get_mut(..size_of::<T>()).ok_or(PodError::BufferFull)?;
+        buf.copy_from_slice(bytemuck::bytes_of(value));
+        Ok(buf)
+    }
+}
+
+impl<T: CheckedBitPattern> Decode<T> for PodCodec {
+    fn decode(&self, msg: &[u8]) -> Result<T, PodError> {
+        if msg.len() != size_of::<T>() {
+            return Err(PodError::InvalidSize {
+                expected: size_of::<T>(),
+                actual: msg.len(),
+            });
+        }
+        bytemuck::checked::try_pod_read_unaligned(msg).map_err(|_| PodError::InvalidBitPattern)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use bytemuck::{CheckedBitPattern, NoUninit, Pod, Zeroable};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+    #[repr(C)]
+    struct Sample {
+        time: u64,
+        values: [i16; 4],
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, NoUninit, CheckedBitPattern)]
+    #[repr(u8)]
+    enum Mode {
+        Standby = 1,
+        Active = 2,
+    }
+
+    #[test]
+    fn pod_queuing() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            // One byte larger than the message, so that it is not aligned to `u64`
+            let mut storage = [0u8; 17];
+            let buf = &mut storage[1..];
+
+            let sample = Sample {
+                time: u64::MAX,
+                values: [1, -1, 2, -2],
+            };
+            src_port
+                .send_type_buf_with(&PodCodec, sample, SystemTime::Infinite, buf)
+                .unwrap();
+            let (rec, _) = dest_port
+                .recv_type_buf_with::<_, Sample>(&PodCodec, SystemTime::Infinite, buf)
+                .unwrap();
+            assert_eq!(sample, rec);
+
+            src_port
+                .send_type_buf_with(&PodCodec, Mode::Active, SystemTime::Infinite, buf)
+                .unwrap();
+            src_port
+                .send_type_buf_with(&PodCodec, 3u8, SystemTime::Infinite, buf)
+                .unwrap();
+            let (rec, _) = dest_port
+                .recv_type_buf_with::<_, Mode>(&PodCodec, SystemTime::Infinite, buf)
+                .unwrap();
+            assert_eq!(Mode::Active, rec);
+            assert_ne!(Mode::Standby, rec);
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, Mode>(&PodCodec, SystemTime::Infinite, buf),
+                Err(QueuingRecvBufError::Postcard(
+                    PodError::InvalidBitPattern,
+                    [3]
+                ))
+            ));
+
+            src_port
+                .send_type_buf_with(&PodCodec, 7u32, SystemTime::Infinite, buf)
+                .unwrap();
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, u64>(&PodCodec, SystemTime::Infinite, buf),
+                Err(QueuingRecvBufError::Postcard(
+                    PodError::InvalidSize {
+                        expected: 8,
+                        actual: 4
+                    },
+                    _
+                ))
+            ));
+            assert!(matches!(
+                src_port.send_type_buf_with(&PodCodec, sample, SystemTime::Infinite, &mut [0; 8]),
+                Err(SendError::Postcard(PodError::BufferFull))
+            ));
+        })
+    }
+}
@@ -0,0 +1,823 @@
+//! Typed ports and declarative partition interfaces
+//!
+//! Typed port wrappers fix the type of sent and received values and the codec used for them,
+//! and are created with a message size computed from the type's [`MaxSize`]
+//! unless a larger one is configured using `size = ...`.
+//! The codec applies to every value, e.g. [`Validated`](crate::validate::Validated)
+//! validates every sent and received value.
+//! The [`PartitionPorts`] derive macro generates a constructor
+//! creating every port of a struct during cold start.
+//!
+//! # Example
+//! ```rust
+//! use a653rs_postcard::prelude::*;
+//! # use a653rs::prelude::*;
+//! # use mock::MockHyp as Hypervisor;
+//! # #[path = "../tests/mock.rs"]
+//! # mod mock;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, MaxSize)]
+//! enum Command {
+//!     Start,
+//!     Stop,
+//! }
+//!
+//! #[derive(Serialize, Deserialize, MaxSize)]
+//! struct Position {
+//!     x: f32,
+//!     y: f32,
+//! }
+//!
+//! #[derive(PartitionPorts)]
+//! struct Ports<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+//!     #[queuing(name = "CMD", depth = 16)]
+//!     cmd: TypedQueuingPortReceiver<Command, H>,
+//!     #[queuing(name = "ACK", depth = 16, discipline = "priority")]
+//!     ack: TypedQueuingPortSender<bool, H>,
+//!     #[sampling(name = "POSITION", refresh_ms = 100, size = 16)]
+//!     position: TypedSamplingPortDestination<Position, H>,
+//! }
+//!
+//! # Hypervisor::run_test(|mut ctx| {
+//! let ports = Ports::create(&mut ctx).unwrap();
+//! assert_eq!(Command::POSTCARD_MAX_SIZE, ports.cmd.port().size());
+//! assert_eq!(16, ports.position.port().size());
+//! # })
+//! ```
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use a653rs::prelude::*;
+pub use a653rs_postcard_derive::PartitionPorts;
+pub use postcard::experimental::max_size::MaxSize;
+
+use crate::codec::{Decode, Encode, Postcard};
+use crate::error::*;
+use crate::queuing::QueuingPortReceiverExt;
+use crate::sampling::SamplingPortDestinationExt;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Port which can be created by [`PartitionPorts`] from a `#[queuing(...)]` field
+pub trait CreateQueuingPort<H>: Sized {
+    /// Maximum size of an encoded value, the default message size of the port
+    const MAX_SIZE: usize;
+
+    /// Create the port with the message size `size`
+    fn create_queuing_port(
+        ctx: &mut StartContext<H>,
+        name: Name,
+        size: MessageSize,
+        depth: MessageRange,
+        discipline: QueuingDiscipline,
+    ) -> Result<Self, Error>;
+}
+
+/// Port which can be created by [`PartitionPorts`] from a `#[sampling(...)]` field
+pub trait CreateSamplingPort<H>: Sized {
+    /// Maximum size of an encoded value, the default message size of the port
+    const MAX_SIZE: usize;
+
+    /// Whether the port requires `refresh_ms = ...`, which is checked at compile time
+    ///
+    /// # Example
+    /// ```rust,compile_fail
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    ///
+    /// #[derive(PartitionPorts)]
+    /// struct Ports<H: ApexSamplingPortP4Ext> {
+    ///     #[sampling(name = "POSITION")]
+    ///     position: TypedSamplingPortDestination<u32, H>,
+    /// }
+    ///
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// let ports = Ports::create(&mut ctx).unwrap();
+    /// # })
+    /// ```
+    const REQUIRES_REFRESH: bool = false;
+
+    /// Create the port with the message size `size`
+    ///
+    /// `refresh` is ignored by sources.
+    fn create_sampling_port(
+        ctx: &mut StartContext<H>,
+        name: Name,
+        size: MessageSize,
+        refresh: Duration,
+    ) -> Result<Self, Error>;
+}
+
+/// Queuing port sender of values of type `T`, encoded using the codec `C`
+#[derive(Debug)]
+pub struct TypedQueuingPortSender<T, Q: ApexQueuingPortP4Ext, C = Postcard> {
+    port: QueuingPortSender<Q>,
+    codec: C,
+    _t: PhantomData<fn(T)>,
+}
+
+impl<T, Q: ApexQueuingPortP4Ext> TypedQueuingPortSender<T, Q> {
+    /// Send values of type `T` via `port`
+    pub fn new(port: QueuingPortSender<Q>) -> Self {
+        Self {
+            port,
+            codec: Postcard,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, Q: ApexQueuingPortP4Ext, C> TypedQueuingPortSender<T, Q, C> {
+    /// Encode values using `codec` instead
+    pub fn with_codec<D>(self, codec: D) -> TypedQueuingPortSender<T, Q, D> {
+        TypedQueuingPortSender {
+            port: self.port,
+            codec,
+            _t: PhantomData,
+        }
+    }
+
+    /// Send a value, see [`QueuingPortSenderExt::send_type`](crate::queuing::QueuingPortSenderExt::send_type)
+    #[cfg(feature = "alloc")]
+    pub fn send_type(&self, p: &T, timeout: SystemTime) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        self.send_type_with(&self.codec, p, timeout)
+    }
+
+    /// Send a value, see [`QueuingPortSenderExt::send_type_buf`](crate::queuing::QueuingPortSenderExt::send_type_buf)
+    pub fn send_type_buf(
+        &self,
+        p: &T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        self.send_type_buf_with(&self.codec, p, timeout, buf)
+    }
+
+    /// Send a value using another `codec`, see [`QueuingPortSenderExt::send_type_with`](crate::queuing::QueuingPortSenderExt::send_type_with)
+    #[cfg(feature = "alloc")]
+    pub fn send_type_with<D>(
+        &self,
+        codec: &D,
+        p: &T,
+        timeout: SystemTime,
+    ) -> Result<(), SendError<D::Error>>
+    where
+        D: Encode<T>,
+    {
+        let mut buf = vec![0; self.port.size()];
+        self.send_type_buf_with(codec, p, timeout, &mut buf)
+    }
+
+    /// Send a value using another `codec`, see [`QueuingPortSenderExt::send_type_buf_with`](crate::queuing::QueuingPortSenderExt::send_type_buf_with)
+    pub fn send_type_buf_with<D>(
+        &self,
+        codec: &D,
+        p: &T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<D::Error>>
+    where
+        D: Encode<T>,
+    {
+        let msg = codec.encode(p, buf).map_err(SendError::Postcard)?;
+        self.port.send(msg, timeout).map_err(SendError::from)
+    }
+
+    /// Codec encoding the values
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortSender<Q> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying queuing port
+    pub fn into_inner(self) -> QueuingPortSender<Q> {
+        self.port
+    }
+}
+
+impl<T, Q, C> CreateQueuingPort<Q> for TypedQueuingPortSender<T, Q, C>
+where
+    T: MaxSize,
+    Q: ApexQueuingPortP4Ext,
+    C: Default,
+{
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+
+    fn create_queuing_port(
+        ctx: &mut StartContext<Q>,
+        name: Name,
+        size: MessageSize,
+        depth: MessageRange,
+        discipline: QueuingDiscipline,
+    ) -> Result<Self, Error> {
+        let port = ctx.create_queuing_port_sender(name, size, depth, discipline)?;
+        Ok(TypedQueuingPortSender::new(port).with_codec(C::default()))
+    }
+}
+
+/// Queuing port receiver of values of type `T`, decoded using the codec `C`
+///
+/// Every received value is decoded using the codec,
+/// e.g. [`Validated`](crate::validate::Validated) to validate every value.
+#[derive(Debug)]
+pub struct TypedQueuingPortReceiver<T, Q: ApexQueuingPortP4Ext, C = Postcard> {
+    port: QueuingPortReceiver<Q>,
+    codec: C,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T, Q: ApexQueuingPortP4Ext> TypedQueuingPortReceiver<T, Q> {
+    /// Receive values of type `T` via `port`
+    pub fn new(port: QueuingPortReceiver<Q>) -> Self {
+        Self {
+            port,
+            codec: Postcard,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, Q: ApexQueuingPortP4Ext, C> TypedQueuingPortReceiver<T, Q, C> {
+    /// Decode values using `codec` instead
+    pub fn with_codec<D>(self, codec: D) -> TypedQueuingPortReceiver<T, Q, D> {
+        TypedQueuingPortReceiver {
+            port: self.port,
+            codec,
+            _t: PhantomData,
+        }
+    }
+
+    /// Receive a value, see [`QueuingPortReceiverExt::recv_type`]
+    #[cfg(feature = "alloc")]
+    pub fn recv_type(
+        &self,
+        timeout: SystemTime,
+    ) -> Result<(T, QueueOverflow), QueuingRecvError<C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_type_with(&self.codec, timeout)
+    }
+
+    /// Receive a value, see [`QueuingPortReceiverExt::recv_type_buf`]
+    pub fn recv_type_buf<'a>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_type_buf_with(&self.codec, timeout, buf)
+    }
+
+    /// Receive a value if available, see [`QueuingPortReceiverExt::try_recv_type_buf`]
+    pub fn try_recv_type_buf<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.try_recv_type_buf_with(&self.codec, buf)
+    }
+
+    /// Receive a value into `place`, see [`QueuingPortReceiverExt::recv_into`]
+    pub fn recv_into<'a>(
+        &self,
+        place: &mut T,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_into_with(&self.codec, place, timeout, buf)
+    }
+
+    /// Receive a value using another `codec`, see [`QueuingPortReceiverExt::recv_type_with`]
+    #[cfg(feature = "alloc")]
+    pub fn recv_type_with<D>(
+        &self,
+        codec: &D,
+        timeout: SystemTime,
+    ) -> Result<(T, QueueOverflow), QueuingRecvError<D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_type_with(codec, timeout)
+    }
+
+    /// Receive a value using another `codec`, see [`QueuingPortReceiverExt::recv_type_buf_with`]
+    pub fn recv_type_buf_with<'a, D>(
+        &self,
+        codec: &D,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a, D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_type_buf_with(codec, timeout, buf)
+    }
+
+    /// Receive a value if available using another `codec`, see [`QueuingPortReceiverExt::try_recv_type_buf_with`]
+    pub fn try_recv_type_buf_with<'a, D>(
+        &self,
+        codec: &D,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a, D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.try_recv_type_buf_with(codec, buf)
+    }
+
+    /// Receive a value into `place` using another `codec`, see [`QueuingPortReceiverExt::recv_into_with`]
+    pub fn recv_into_with<'a, D>(
+        &self,
+        codec: &D,
+        place: &mut T,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a, D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_into_with(codec, place, timeout, buf)
+    }
+
+    /// Codec decoding the values
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Underlying queuing port
+    pub fn port(&self) -> &QueuingPortReceiver<Q> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying queuing port
+    pub fn into_inner(self) -> QueuingPortReceiver<Q> {
+        self.port
+    }
+}
+
+impl<T, Q, C> CreateQueuingPort<Q> for TypedQueuingPortReceiver<T, Q, C>
+where
+    T: MaxSize,
+    Q: ApexQueuingPortP4Ext,
+    C: Default,
+{
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+
+    fn create_queuing_port(
+        ctx: &mut StartContext<Q>,
+        name: Name,
+        size: MessageSize,
+        depth: MessageRange,
+        discipline: QueuingDiscipline,
+    ) -> Result<Self, Error> {
+        let port = ctx.create_queuing_port_receiver(name, size, depth, discipline)?;
+        Ok(TypedQueuingPortReceiver::new(port).with_codec(C::default()))
+    }
+}
+
+/// Sampling port source of values of type `T`, encoded using the codec `C`
+#[derive(Debug)]
+pub struct TypedSamplingPortSource<T, S: ApexSamplingPortP4Ext, C = Postcard> {
+    port: SamplingPortSource<S>,
+    codec: C,
+    _t: PhantomData<fn(T)>,
+}
+
+impl<T, S: ApexSamplingPortP4Ext> TypedSamplingPortSource<T, S> {
+    /// Send values of type `T` via `port`
+    pub fn new(port: SamplingPortSource<S>) -> Self {
+        Self {
+            port,
+            codec: Postcard,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, S: ApexSamplingPortP4Ext, C> TypedSamplingPortSource<T, S, C> {
+    /// Encode values using `codec` instead
+    pub fn with_codec<D>(self, codec: D) -> TypedSamplingPortSource<T, S, D> {
+        TypedSamplingPortSource {
+            port: self.port,
+            codec,
+            _t: PhantomData,
+        }
+    }
+
+    /// Send a value, see [`SamplingPortSourceExt::send_type`](crate::sampling::SamplingPortSourceExt::send_type)
+    #[cfg(feature = "alloc")]
+    pub fn send_type(&self, p: &T) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        self.send_type_with(&self.codec, p)
+    }
+
+    /// Send a value, see [`SamplingPortSourceExt::send_type_buf`](crate::sampling::SamplingPortSourceExt::send_type_buf)
+    pub fn send_type_buf(&self, p: &T, buf: &mut [u8]) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        self.send_type_buf_with(&self.codec, p, buf)
+    }
+
+    /// Send a value using another `codec`, see [`SamplingPortSourceExt::send_type_with`](crate::sampling::SamplingPortSourceExt::send_type_with)
+    #[cfg(feature = "alloc")]
+    pub fn send_type_with<D>(&self, codec: &D, p: &T) -> Result<(), SendError<D::Error>>
+    where
+        D: Encode<T>,
+    {
+        let mut buf = vec![0; self.port.size() as usize];
+        self.send_type_buf_with(codec, p, &mut buf)
+    }
+
+    /// Send a value using another `codec`, see [`SamplingPortSourceExt::send_type_buf_with`](crate::sampling::SamplingPortSourceExt::send_type_buf_with)
+    pub fn send_type_buf_with<D>(
+        &self,
+        codec: &D,
+        p: &T,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<D::Error>>
+    where
+        D: Encode<T>,
+    {
+        let msg = codec.encode(p, buf).map_err(SendError::Postcard)?;
+        self.port.send(msg).map_err(SendError::from)
+    }
+
+    /// Codec encoding the values
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Underlying sampling port
+    pub fn port(&self) -> &SamplingPortSource<S> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying sampling port
+    pub fn into_inner(self) -> SamplingPortSource<S> {
+        self.port
+    }
+}
+
+impl<T, S, C> CreateSamplingPort<S> for TypedSamplingPortSource<T, S, C>
+where
+    T: MaxSize,
+    S: ApexSamplingPortP4Ext,
+    C: Default,
+{
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+
+    fn create_sampling_port(
+        ctx: &mut StartContext<S>,
+        name: Name,
+        size: MessageSize,
+        _refresh: Duration,
+    ) -> Result<Self, Error> {
+        let port = ctx.create_sampling_port_source(name, size)?;
+        Ok(TypedSamplingPortSource::new(port).with_codec(C::default()))
+    }
+}
+
+/// Sampling port destination of values of type `T`, decoded using the codec `C`
+///
+/// Every received value is decoded using the codec,
+/// e.g. [`Validated`](crate::validate::Validated) to validate every value.
+#[derive(Debug)]
+pub struct TypedSamplingPortDestination<T, S: ApexSamplingPortP4Ext, C = Postcard> {
+    port: SamplingPortDestination<S>,
+    codec: C,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T, S: ApexSamplingPortP4Ext> TypedSamplingPortDestination<T, S> {
+    /// Receive values of type `T` via `port`
+    pub fn new(port: SamplingPortDestination<S>) -> Self {
+        Self {
+            port,
+            codec: Postcard,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, S: ApexSamplingPortP4Ext, C> TypedSamplingPortDestination<T, S, C> {
+    /// Decode values using `codec` instead
+    pub fn with_codec<D>(self, codec: D) -> TypedSamplingPortDestination<T, S, D> {
+        TypedSamplingPortDestination {
+            port: self.port,
+            codec,
+            _t: PhantomData,
+        }
+    }
+
+    /// Receive a value, see [`SamplingPortDestinationExt::recv_type`]
+    #[cfg(feature = "alloc")]
+    pub fn recv_type(&self) -> Result<(Validity, T), SamplingRecvError<C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_type_with(&self.codec)
+    }
+
+    /// Receive a value, see [`SamplingPortDestinationExt::recv_type_buf`]
+    pub fn recv_type_buf<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<(Validity, T), SamplingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_type_buf_with(&self.codec, buf)
+    }
+
+    /// Receive a value into `place`, see [`SamplingPortDestinationExt::recv_into`]
+    pub fn recv_into<'a>(
+        &self,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        self.port.recv_into_with(&self.codec, place, buf)
+    }
+
+    /// Receive a value using another `codec`, see [`SamplingPortDestinationExt::recv_type_with`]
+    #[cfg(feature = "alloc")]
+    pub fn recv_type_with<D>(&self, codec: &D) -> Result<(Validity, T), SamplingRecvError<D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_type_with(codec)
+    }
+
+    /// Receive a value using another `codec`, see [`SamplingPortDestinationExt::recv_type_buf_with`]
+    pub fn recv_type_buf_with<'a, D>(
+        &self,
+        codec: &D,
+        buf: &'a mut [u8],
+    ) -> Result<(Validity, T), SamplingRecvBufError<'a, D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_type_buf_with(codec, buf)
+    }
+
+    /// Receive a value into `place` using another `codec`, see [`SamplingPortDestinationExt::recv_into_with`]
+    pub fn recv_into_with<'a, D>(
+        &self,
+        codec: &D,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a, D::Error>>
+    where
+        D: Decode<T>,
+    {
+        self.port.recv_into_with(codec, place, buf)
+    }
+
+    /// Codec decoding the values
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Underlying sampling port
+    pub fn port(&self) -> &SamplingPortDestination<S> {
+        &self.port
+    }
+
+    /// Consume the wrapper, returning the underlying sampling port
+    pub fn into_inner(self) -> SamplingPortDestination<S> {
+        self.port
+    }
+}
+
+impl<T, S, C> CreateSamplingPort<S> for TypedSamplingPortDestination<T, S, C>
+where
+    T: MaxSize,
+    S: ApexSamplingPortP4Ext,
+    C: Default,
+{
+    const MAX_SIZE: usize = T::POSTCARD_MAX_SIZE;
+    const REQUIRES_REFRESH: bool = true;
+
+    fn create_sampling_port(
+        ctx: &mut StartContext<S>,
+        name: Name,
+        size: MessageSize,
+        refresh: Duration,
+    ) -> Result<Self, Error> {
+        let port = ctx.create_sampling_port_destination(name, size, refresh)?;
+        Ok(TypedSamplingPortDestination::new(port).with_codec(C::default()))
+    }
+}
+
+/// Items used by the code generated by [`PartitionPorts`]
+#[doc(hidden)]
+pub mod __private {
+    use core::str::FromStr;
+
+    pub use a653rs::prelude::{Error, MessageSize, Name, QueuingDiscipline, StartContext};
+
+    /// Port name, failing with [`Error::InvalidConfig`] if it is too long
+    pub fn port_name(name: &str) -> Result<Name, Error> {
+        Name::from_str(name).map_err(|_| Error::InvalidConfig)
+    }
+
+    /// Message size, failing with [`Error::InvalidConfig`] if it is too large
+    pub fn message_size(size: usize) -> Result<MessageSize, Error> {
+        MessageSize::try_from(size).map_err(|_| Error::InvalidConfig)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use a653rs::prelude::{ApexQueuingPortP4Ext, ApexSamplingPortP4Ext, Error, SystemTime};
+    use mock::MockHyp;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, MaxSize)]
+    struct Telemetry {
+        counter: u32,
+        healthy: bool,
+    }
+
+    #[derive(PartitionPorts)]
+    struct Sender<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "EVENTS", depth = 4, discipline = "priority")]
+        events: TypedQueuingPortSender<u16, H>,
+        #[sampling(name = "TM")]
+        telemetry: TypedSamplingPortSource<Telemetry, H>,
+    }
+
+    #[derive(PartitionPorts)]
+    struct Receiver<H>
+    where
+        H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext,
+    {
+        #[queuing(name = "EVENTS", depth = 4)]
+        events: TypedQueuingPortReceiver<u16, H>,
+        #[sampling(name = "TM", refresh_ms = 500)]
+        telemetry: TypedSamplingPortDestination<Telemetry, H>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, MaxSize)]
+    struct Percent(u8);
+
+    impl Validate for Percent {
+        fn validate(&self) -> Result<(), ValidationError> {
+            if self.0 > 100 {
+                return Err(ValidationError("above 100 %"));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(PartitionPorts)]
+    struct Validating<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "LEVEL", depth = 4)]
+        src: TypedQueuingPortSender<Percent, H>,
+        #[queuing(name = "LEVEL", depth = 4, codec = Validated(Postcard))]
+        dest: TypedQueuingPortReceiver<Percent, H, Validated>,
+        #[sampling(name = "SETPOINT")]
+        setpoint: TypedSamplingPortSource<Percent, H, Validated>,
+    }
+
+    #[derive(PartitionPorts)]
+    struct Sized<H: ApexQueuingPortP4Ext + ApexSamplingPortP4Ext> {
+        #[queuing(name = "COUNTER", depth = 4, size = 8)]
+        src: TypedQueuingPortSender<u16, H>,
+        #[queuing(name = "COUNTER", depth = 4, size = 8)]
+        dest: TypedQueuingPortReceiver<u16, H>,
+        #[sampling(name = "TM", refresh_ms = 500, size = 16)]
+        telemetry: TypedSamplingPortDestination<Telemetry, H>,
+    }
+
+    #[derive(PartitionPorts)]
+    struct TooLong<H: ApexQueuingPortP4Ext> {
+        #[queuing(name = "A_PORT_NAME_LONGER_THAN_ALLOWED_BY_ARINC_653", depth = 1)]
+        _port: TypedQueuingPortSender<u8, H>,
+    }
+
+    #[test]
+    fn partition_ports() {
+        MockHyp::run_test(|mut ctx| {
+            let sender = Sender::create(&mut ctx).unwrap();
+            let receiver = Receiver::create(&mut ctx).unwrap();
+            assert_eq!(3, sender.events.port().size());
+            assert_eq!(6, receiver.telemetry.port().size());
+            assert!(matches!(
+                TooLong::create(&mut ctx),
+                Err(Error::InvalidConfig)
+            ));
+
+            let mut buf = [0; 6];
+            sender
+                .events
+                .send_type_buf(&1000, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let telemetry = Telemetry {
+                counter: u32::MAX,
+                healthy: true,
+            };
+            sender
+                .telemetry
+                .send_type_buf(&telemetry, &mut buf)
+                .unwrap();
+
+            let (rec, _) = receiver
+                .events
+                .recv_type_buf(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(1000, rec);
+            let (_, rec) = receiver.telemetry.recv_type_buf(&mut buf).unwrap();
+            assert_eq!(telemetry, rec);
+        })
+    }
+
+    #[test]
+    fn partition_ports_codec() {
+        MockHyp::run_test(|mut ctx| {
+            let ports = Validating::create(&mut ctx).unwrap();
+            let mut buf = [0; 2];
+
+            ports
+                .src
+                .send_type_buf(&Percent(120), SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert!(matches!(
+                ports.dest.recv_type_buf(SystemTime::Infinite, &mut buf),
+                Err(QueuingRecvBufError::Validation(_, [120]))
+            ));
+            assert!(matches!(
+                ports.setpoint.send_type_buf(&Percent(120), &mut buf),
+                Err(SendError::Postcard(ValidatedError::Validation(_)))
+            ));
+        })
+    }
+
+    #[test]
+    fn partition_ports_size() {
+        MockHyp::run_test(|mut ctx| {
+            let ports = Sized::create(&mut ctx).unwrap();
+            assert_eq!(8, ports.src.port().size());
+            assert_eq!(8, ports.dest.port().size());
+            assert_eq!(16, ports.telemetry.port().size());
+
+            let mut buf = [0; 8];
+            assert!(matches!(ports.dest.try_recv_type_buf(&mut buf), Ok(None)));
+            ports
+                .src
+                .send_type_buf(&7, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            ports
+                .src
+                .send_type_buf_with(&Postcard, &8, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut rec = 0;
+            ports
+                .dest
+                .recv_into(&mut rec, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(7, rec);
+            let (rec, _) = ports.dest.try_recv_type_buf(&mut buf).unwrap().unwrap();
+            assert_eq!(8, rec);
+        })
+    }
+}
@@ -1,5 +1,34 @@
 //! Convenience prelude for simple import
 
+pub use crate::batch::*;
+pub use crate::cache::*;
+pub use crate::ccsds::*;
+pub use crate::cobs::*;
+pub use crate::codec::*;
+#[cfg(feature = "config")]
+pub use crate::config::*;
 pub use crate::error::*;
+pub use crate::fan_out::*;
+pub use crate::forward::*;
+#[cfg(feature = "icd")]
+pub use crate::icd::*;
+#[cfg(feature = "icd")]
+pub use crate::inspect::*;
+#[cfg(any(feature = "embedded-io", feature = "std"))]
+pub use crate::io::*;
+pub use crate::limits::*;
+pub use crate::on_change::*;
+#[cfg(feature = "bytemuck")]
+pub use crate::pod::*;
+#[cfg(feature = "derive")]
+pub use crate::ports::*;
 pub use crate::queuing::*;
+#[cfg(feature = "record")]
+pub use crate::record::*;
 pub use crate::sampling::*;
+pub use crate::select::*;
+#[cfg(feature = "std")]
+pub use crate::udp::*;
+pub use crate::validate::*;
+pub use crate::version::*;
+pub use crate::voting::*;
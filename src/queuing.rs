@@ -6,17 +6,89 @@ use alloc::vec;
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+use core::marker::PhantomData;
+use core::time::Duration;
+
 use a653rs::prelude::*;
-use postcard::de_flavors::Slice as DeSlice;
-use postcard::ser_flavors::Slice as SerSlice;
 use serde::{Deserialize, Serialize};
 
+use crate::codec::{Decode, Encode, Postcard};
 use crate::error::*;
 
+/// Iterator over all messages queued in a queuing port
+///
+/// Created by [`QueuingPortReceiverExt::iter_recv`] or [`QueuingPortReceiverExt::iter_recv_with`].
+#[derive(Debug)]
+pub struct QueuingRecvIter<'a, R, T, C = Postcard> {
+    port: &'a R,
+    codec: &'a C,
+    buf: &'a mut [u8],
+    len: usize,
+    remaining: MessageRange,
+    overflow: QueueOverflow,
+    _t: PhantomData<T>,
+}
+
+impl<R, T, C> QueuingRecvIter<'_, R, T, C> {
+    /// Whether any message received so far reported a [`QueueOverflow`]
+    pub fn overflow(&self) -> QueueOverflow {
+        self.overflow
+    }
+
+    /// Last received message, e.g. the one which just failed to decode
+    pub fn msg(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl<Q, T, C> Iterator for QueuingRecvIter<'_, QueuingPortReceiver<Q>, T, C>
+where
+    Q: ApexQueuingPortP4Ext,
+    C: Decode<T>,
+{
+    type Item = Result<T, QueuingRecvIterError<C::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.len = 0;
+        self.len = match self
+            .port
+            .receive(self.buf, SystemTime::Normal(Duration::ZERO))
+        {
+            Ok((msg, overflow)) => {
+                self.overflow |= overflow;
+                msg.len()
+            }
+            Err(Error::NotAvailable) => {
+                self.remaining = 0;
+                return None;
+            }
+            Err(e) => {
+                self.remaining = 0;
+                return Some(Err(QueuingRecvIterError::Apex(e)));
+            }
+        };
+        Some(
+            self.codec
+                .decode(&self.buf[..self.len])
+                .map_err(QueuingRecvIterError::decode::<C>),
+        )
+    }
+}
+
 /// Postcard extension trait for queuing port sender
+///
+/// All sending functions accept either owned values or references,
+/// as `&T` is [`Serialize`] whenever `T` is.
 pub trait QueuingPortSenderExt {
     /// Send a type using an a653rs [`QueuingPortSender`]
     ///
+    /// Serializes into a buffer of the port's message size,
+    /// so values too large for the port fail with [`postcard::Error::SerializeBufferFull`].
+    ///
     /// # Example
     /// ```rust
     /// use a653rs_postcard::prelude::*;
@@ -66,6 +138,142 @@ pub trait QueuingPortSenderExt {
     fn send_type_buf<T>(&self, p: T, timeout: SystemTime, buf: &mut [u8]) -> Result<(), SendError>
     where
         T: Serialize;
+
+    /// Send every item of `items` as a separate message using an a653rs [`QueuingPortSender`]
+    ///
+    /// Items may be references, so that large values do not need to be cloned.
+    /// Sending stops at the first item which fails to be serialized or sent.
+    /// Like [`send_type`](Self::send_type), items too large for the port
+    /// fail with [`postcard::Error::SerializeBufferFull`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let events = [String::from("Start"), String::from("Stop")];
+    /// port.send_types(&events, SystemTime::Infinite).unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn send_types<I>(&self, items: I, timeout: SystemTime) -> Result<(), SendError>
+    where
+        I: IntoIterator,
+        I::Item: Serialize;
+
+    /// Send every item of `items` as a separate message using an a653rs [`QueuingPortSender`]
+    ///
+    /// Requires a buffer `buf` for serialization, which is reused for every item.
+    /// Items may be references, so that large values do not need to be cloned.
+    /// Sending stops at the first item which fails to be serialized or sent.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let events = [String::from("Start"), String::from("Stop")];
+    /// let mut buf = [0; 500];
+    /// port.send_types_buf(&events, SystemTime::Infinite, &mut buf).unwrap();
+    /// # })
+    /// ```
+    fn send_types_buf<I>(
+        &self,
+        items: I,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError>
+    where
+        I: IntoIterator,
+        I::Item: Serialize;
+
+    /// Send a type using an a653rs [`QueuingPortSender`] and the given `codec`
+    ///
+    /// Encodes into a buffer of the port's message size,
+    /// so values too large for the port fail with a codec error like `send_type`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// port.send_type_with(&Postcard, String::from("Typed Data"), SystemTime::Infinite)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn send_type_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        timeout: SystemTime,
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>;
+
+    /// Send a type using an a653rs [`QueuingPortSender`] and the given `codec`
+    ///
+    /// Requires a buffer `buf` for encoding.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortSender<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// port.send_type_buf_with(&Postcard, String::from("Typed Data"), SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    fn send_type_buf_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>;
 }
 
 /// Postcard extension trait for queuing ports receiver
@@ -126,13 +334,366 @@ pub trait QueuingPortReceiverExt {
     /// port.recv_type_buf::<String>(SystemTime::Infinite, &mut buf).unwrap();
     /// # })
     /// ```
-    fn recv_type_buf<'a, T>(
-        &'a self,
+    fn recv_type_buf<'a, T>(
+        &'a self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>;
+
+    /// Receive a type using an a653rs [`QueuingPortReceiver`] without waiting
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Polls the port with a zero timeout and returns `None` if the queue is empty,
+    /// instead of [`Error::NotAvailable`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::default(), SystemTime::Infinite, &mut buf).unwrap();
+    /// if let Some((value, overflow)) = port.try_recv_type_buf::<String>(&mut buf).unwrap() {
+    ///     // Process value
+    /// }
+    /// # })
+    /// ```
+    fn try_recv_type_buf<'a, T>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>;
+
+    /// Receive a type using an a653rs [`QueuingPortReceiver`], overwriting an existing value
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// The received value is deserialized directly into `place`,
+    /// reusing its allocations, see [`Decode::decode_in_place`].
+    /// On failure the contents of `place` are unspecified.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// let mut readings = [0u32; 32];
+    /// # src_port.send_type_buf(&[1u32; 32], SystemTime::Infinite, &mut buf).unwrap();
+    /// port.recv_into(&mut readings, SystemTime::Infinite, &mut buf).unwrap();
+    /// # })
+    /// ```
+    fn recv_into<'a, T>(
+        &self,
+        place: &mut T,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>;
+
+    /// Receive all messages currently queued in an a653rs [`QueuingPortReceiver`]
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Messages are received with a zero timeout until the queue is empty,
+    /// but at most as many messages as the queue can hold, bounding the execution time.
+    /// Every message is passed to `f`, either deserialized or as a deserialization error.
+    ///
+    /// Returns whether any received message reported a [`QueueOverflow`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_types_buf([1u32, 2, 3], SystemTime::Infinite, &mut buf).unwrap();
+    /// let overflow = port
+    ///     .recv_all_buf::<u32, _>(&mut buf, |msg| match msg {
+    ///         Ok(value) => { /* Process value */ }
+    ///         Err(e) => { /* Report error */ }
+    ///     })
+    ///     .unwrap();
+    /// # })
+    /// ```
+    fn recv_all_buf<T, F>(&self, buf: &mut [u8], f: F) -> Result<QueueOverflow, Error>
+    where
+        T: for<'b> Deserialize<'b>,
+        F: FnMut(Result<T, QueuingRecvBufError<'_>>);
+
+    /// Iterate over all messages currently queued in an a653rs [`QueuingPortReceiver`]
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Messages are received with a zero timeout until the queue is empty,
+    /// but at most as many messages as the queue can hold, bounding the execution time.
+    /// Iteration ends after the first a653rs error other than [`Error::NotAvailable`].
+    /// Messages failing to deserialize are reported and skipped,
+    /// their data is available from [`QueuingRecvIter::msg`] until the next message is received.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_types_buf([1u32, 2, 3], SystemTime::Infinite, &mut buf).unwrap();
+    /// let mut iter = port.iter_recv::<u32>(&mut buf);
+    /// let mut sum = 0;
+    /// while let Some(value) = iter.next() {
+    ///     match value {
+    ///         Ok(value) => sum += value,
+    ///         Err(e) => { /* Report error and iter.msg() */ }
+    ///     }
+    /// }
+    /// let overflow = iter.overflow();
+    /// # assert_eq!(6, sum);
+    /// # })
+    /// ```
+    fn iter_recv<'a, T>(&'a self, buf: &'a mut [u8]) -> QueuingRecvIter<'a, Self, T>
+    where
+        Self: Sized,
+        T: for<'b> Deserialize<'b>;
+
+    /// Iterate over all messages currently queued, decoding them using the given `codec`
+    ///
+    /// See [`QueuingPortReceiverExt::iter_recv`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_types_buf([1u32, 2, 3], SystemTime::Infinite, &mut buf).unwrap();
+    /// let sum: u32 = port
+    ///     .iter_recv_with::<_, u32>(&Postcard, &mut buf)
+    ///     .flatten()
+    ///     .sum();
+    /// # assert_eq!(6, sum);
+    /// # })
+    /// ```
+    fn iter_recv_with<'a, C, T>(
+        &'a self,
+        codec: &'a C,
+        buf: &'a mut [u8],
+    ) -> QueuingRecvIter<'a, Self, T, C>
+    where
+        Self: Sized,
+        C: Decode<T>;
+
+    /// Receive a type using an a653rs [`QueuingPortReceiver`] and the given `codec`
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # src_port.send_type(String::from("Typed Data"), SystemTime::Infinite).unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let (msg, _): (String, _) = port.recv_type_with(&Postcard, SystemTime::Infinite).unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn recv_type_with<C, T>(
+        &self,
+        codec: &C,
+        timeout: SystemTime,
+    ) -> Result<(T, QueueOverflow), QueuingRecvError<C::Error>>
+    where
+        C: Decode<T>;
+
+    /// Receive a type using an a653rs [`QueuingPortReceiver`] and the given `codec`
+    ///
+    /// Requires a buffer `buf` for receiving and decoding the data.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::from("Typed Data"), SystemTime::Infinite, &mut buf).unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let (msg, _): (String, _) = port
+    ///     .recv_type_buf_with(&Postcard, SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    fn recv_type_buf_with<'a, C, T>(
+        &self,
+        codec: &C,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>;
+
+    /// Receive a type using the given `codec` if a message is available, without waiting
+    ///
+    /// Requires a buffer `buf` for receiving and decoding the data.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::from("Typed Data"), SystemTime::Infinite, &mut buf).unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let msg = port.try_recv_type_buf_with::<_, String>(&Postcard, &mut buf).unwrap();
+    /// assert!(msg.is_some());
+    /// # })
+    /// ```
+    fn try_recv_type_buf_with<'a, C, T>(
+        &self,
+        codec: &C,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>;
+
+    /// Receive a type into an existing `place` using the given `codec`
+    ///
+    /// Requires a buffer `buf` for receiving and decoding the data.
+    /// On failure the contents of `place` are unspecified, see [`Decode::decode_in_place`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::from("Typed Data"), SystemTime::Infinite, &mut buf).unwrap();
+    ///
+    /// let port: QueuingPortReceiver<Hypervisor> = port;
+    /// let mut msg = String::new();
+    /// port.recv_into_with(&Postcard, &mut msg, SystemTime::Infinite, &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    fn recv_into_with<'a, C, T>(
+        &self,
+        codec: &C,
+        place: &mut T,
         timeout: SystemTime,
         buf: &'a mut [u8],
-    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a>>
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a, C::Error>>
     where
-        T: for<'b> Deserialize<'b>;
+        C: Decode<T>;
 }
 
 impl<Q: ApexQueuingPortP4Ext> QueuingPortSenderExt for QueuingPortSender<Q> {
@@ -141,16 +702,67 @@ impl<Q: ApexQueuingPortP4Ext> QueuingPortSenderExt for QueuingPortSender<Q> {
     where
         T: Serialize,
     {
-        let msg = postcard::to_allocvec(&p)?;
-        self.send(&msg, timeout).map_err(SendError::from)
+        self.send_type_with(&Postcard, p, timeout)
     }
 
     fn send_type_buf<T>(&self, p: T, timeout: SystemTime, buf: &mut [u8]) -> Result<(), SendError>
     where
         T: Serialize,
     {
-        let buf =
-            postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(&p, SerSlice::new(buf))?;
+        self.send_type_buf_with(&Postcard, p, timeout, buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn send_types<I>(&self, items: I, timeout: SystemTime) -> Result<(), SendError>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        items
+            .into_iter()
+            .try_for_each(|p| self.send_type(p, timeout.clone()))
+    }
+
+    fn send_types_buf<I>(
+        &self,
+        items: I,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        items
+            .into_iter()
+            .try_for_each(|p| self.send_type_buf(p, timeout.clone(), buf))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn send_type_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        timeout: SystemTime,
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        let mut buf = vec![0; self.size()];
+        self.send_type_buf_with(codec, p, timeout, &mut buf)
+    }
+
+    fn send_type_buf_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        timeout: SystemTime,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        let buf = codec.encode(&p, buf).map_err(SendError::Postcard)?;
         self.send(buf, timeout).map_err(SendError::from)
     }
 }
@@ -160,33 +772,159 @@ impl<Q: ApexQueuingPortP4Ext> QueuingPortReceiverExt for QueuingPortReceiver<Q>
     fn recv_type<T>(&self, timeout: SystemTime) -> Result<(T, QueueOverflow), QueuingRecvError>
     where
         T: for<'a> Deserialize<'a>,
+    {
+        self.recv_type_with(&Postcard, timeout)
+    }
+
+    fn recv_type_buf<'a, T>(
+        &self,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.recv_type_buf_with(&Postcard, timeout, buf)
+    }
+
+    fn try_recv_type_buf<'a, T>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.try_recv_type_buf_with(&Postcard, buf)
+    }
+
+    fn recv_into<'a, T>(
+        &self,
+        place: &mut T,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.recv_into_with(&Postcard, place, timeout, buf)
+    }
+
+    fn recv_all_buf<T, F>(&self, buf: &mut [u8], mut f: F) -> Result<QueueOverflow, Error>
+    where
+        T: for<'b> Deserialize<'b>,
+        F: FnMut(Result<T, QueuingRecvBufError<'_>>),
+    {
+        let mut overflow = false;
+        for _ in 0..self.range() {
+            match self.try_recv_type_buf(buf) {
+                Ok(Some((t, o))) => {
+                    overflow |= o;
+                    f(Ok(t))
+                }
+                Ok(None) => break,
+                Err(QueuingRecvBufError::Apex(e)) => return Err(e),
+                Err(e) => f(Err(e)),
+            }
+        }
+        Ok(overflow)
+    }
+
+    fn iter_recv<'a, T>(&'a self, buf: &'a mut [u8]) -> QueuingRecvIter<'a, Self, T>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.iter_recv_with(&Postcard, buf)
+    }
+
+    fn iter_recv_with<'a, C, T>(
+        &'a self,
+        codec: &'a C,
+        buf: &'a mut [u8],
+    ) -> QueuingRecvIter<'a, Self, T, C>
+    where
+        C: Decode<T>,
+    {
+        QueuingRecvIter {
+            port: self,
+            codec,
+            buf,
+            len: 0,
+            remaining: self.range(),
+            overflow: false,
+            _t: PhantomData,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn recv_type_with<C, T>(
+        &self,
+        codec: &C,
+        timeout: SystemTime,
+    ) -> Result<(T, QueueOverflow), QueuingRecvError<C::Error>>
+    where
+        C: Decode<T>,
     {
         let mut buf = vec![0; self.size()];
         let (msg, overflow) = self.receive(&mut buf, timeout)?;
-        match postcard::from_bytes(msg) {
+        match codec.decode(msg) {
             Ok(t) => Ok((t, overflow)),
             Err(e) => {
                 let msg_len = msg.len();
                 buf.truncate(msg_len);
-                Err(QueuingRecvError::Postcard(e, buf))
+                Err(QueuingRecvError::decode::<C>(e, buf))
             }
         }
     }
 
-    fn recv_type_buf<'a, T>(
+    fn recv_type_buf_with<'a, C, T>(
         &self,
+        codec: &C,
         timeout: SystemTime,
         buf: &'a mut [u8],
-    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a>>
+    ) -> Result<(T, QueueOverflow), QueuingRecvBufError<'a, C::Error>>
     where
-        T: for<'b> Deserialize<'b>,
+        C: Decode<T>,
     {
         let (msg, overflow) = self.receive(buf, timeout)?;
-        let msg_slice = DeSlice::new(msg);
-        let mut deserializer = postcard::Deserializer::from_flavor(msg_slice);
-        match T::deserialize(&mut deserializer) {
+        match codec.decode(msg) {
             Ok(t) => Ok((t, overflow)),
-            Err(e) => Err(QueuingRecvBufError::Postcard(e, msg)),
+            Err(e) => Err(QueuingRecvBufError::decode::<C>(e, msg)),
+        }
+    }
+
+    fn try_recv_type_buf_with<'a, C, T>(
+        &self,
+        codec: &C,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(T, QueueOverflow)>, QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        let (msg, overflow) = match self.receive(buf, SystemTime::Normal(Duration::ZERO)) {
+            Ok(received) => received,
+            Err(Error::NotAvailable) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match codec.decode(msg) {
+            Ok(t) => Ok(Some((t, overflow))),
+            Err(e) => Err(QueuingRecvBufError::decode::<C>(e, msg)),
+        }
+    }
+
+    fn recv_into_with<'a, C, T>(
+        &self,
+        codec: &C,
+        place: &mut T,
+        timeout: SystemTime,
+        buf: &'a mut [u8],
+    ) -> Result<QueueOverflow, QueuingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        let (msg, overflow) = self.receive(buf, timeout)?;
+        match codec.decode_in_place(msg, place) {
+            Ok(()) => Ok(overflow),
+            Err(e) => Err(QueuingRecvBufError::decode::<C>(e, msg)),
         }
     }
 }
@@ -201,7 +939,7 @@ mod tests {
     use a653rs::prelude::{Name, SystemTime};
     use mock::MockHyp;
 
-    use crate::prelude::{QueuingPortReceiverExt, QueuingPortSenderExt};
+    use crate::prelude::{QueuingPortReceiverExt, QueuingPortSenderExt, QueuingRecvIterError};
 
     extern crate std;
 
@@ -272,6 +1010,253 @@ mod tests {
         })
     }
 
+    #[test]
+    fn queuing_type_by_ref() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+
+            let msg = String::from("Test");
+            let mut buf = [0; 500];
+
+            src_port
+                .send_type_buf(&msg, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let (rec, _): (String, _) = dest_port
+                .recv_type_buf(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(msg, rec);
+
+            #[cfg(feature = "alloc")]
+            {
+                let msg = String::from("Allocated");
+                src_port.send_type(&msg, SystemTime::Infinite).unwrap();
+                let (rec, _): (String, _) = dest_port.recv_type(SystemTime::Infinite).unwrap();
+                assert_eq!(msg, rec);
+            }
+        })
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn queuing_types_oversized() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+
+            let msgs = [String::from("Ok"), String::from("Too long")];
+            assert!(matches!(
+                src_port.send_types(&msgs, SystemTime::Infinite),
+                Err(crate::error::SendError::Postcard(
+                    postcard::Error::SerializeBufferFull
+                ))
+            ));
+        })
+    }
+
+    #[test]
+    fn queuing_types_buf() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+
+            let msgs = [String::from("First"), String::from("Second")];
+            let mut buf = [0; 500];
+
+            src_port
+                .send_types_buf(&msgs, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            for msg in &msgs {
+                let (rec, _): (String, _) = dest_port
+                    .recv_type_buf(SystemTime::Infinite, &mut buf)
+                    .unwrap();
+                assert_eq!(msg, &rec)
+            }
+        })
+    }
+
+    #[test]
+    fn queuing_recv_into() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+
+            let msg = (String::from("Test"), 42u32);
+            let mut rec = (String::from("Old"), 0u32);
+            let mut buf = [0; 500];
+
+            src_port
+                .send_type_buf(&msg, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            dest_port
+                .recv_into(&mut rec, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(msg, rec);
+
+            src_port
+                .send_type_buf(true, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut rec = String::from("Old");
+            dest_port
+                .recv_into(&mut rec, SystemTime::Infinite, &mut buf)
+                .unwrap_err();
+            assert_eq!("Old", rec);
+
+            // Fails after deserializing the first two elements in place
+            src_port
+                .send_type_buf([1u32, 2], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut rec = [9u32; 4];
+            dest_port
+                .recv_into(&mut rec, SystemTime::Infinite, &mut buf)
+                .unwrap_err();
+            assert_eq!([1, 2, 9, 9], rec)
+        })
+    }
+
+    #[test]
+    fn queuing_try_recv_type_buf() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 500];
+
+            assert!(dest_port
+                .try_recv_type_buf::<u32>(&mut buf)
+                .unwrap()
+                .is_none());
+            src_port
+                .send_type_buf(7u32, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let (rec, _) = dest_port
+                .try_recv_type_buf::<u32>(&mut buf)
+                .unwrap()
+                .unwrap();
+            assert_eq!(7, rec);
+        })
+    }
+
+    #[test]
+    fn queuing_recv_all_buf() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    500,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 500];
+
+            src_port
+                .send_types_buf([1u64, 2, u64::MAX, 3], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut received = std::vec::Vec::new();
+            let mut errors = 0;
+            let overflow = dest_port
+                .recv_all_buf::<u32, _>(&mut buf, |msg| match msg {
+                    Ok(t) => received.push(t),
+                    Err(_) => errors += 1,
+                })
+                .unwrap();
+            assert_eq!([1, 2, 3], received.as_slice());
+            assert_eq!(1, errors);
+            assert!(!overflow);
+
+            src_port
+                .send_types_buf([4u64, u64::MAX, 5], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let mut iter = dest_port.iter_recv::<u32>(&mut buf);
+            assert_eq!(4, iter.next().unwrap().unwrap());
+            assert!(matches!(
+                iter.next(),
+                Some(Err(QueuingRecvIterError::Postcard(_)))
+            ));
+            assert_eq!([255, 255], iter.msg()[..2]);
+            assert_eq!(5, iter.next().unwrap().unwrap());
+            assert!(iter.next().is_none());
+            assert!(!iter.overflow());
+
+            src_port
+                .send_types_buf([6u64, u64::MAX, 7], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let sum: u32 = dest_port.iter_recv::<u32>(&mut buf).flatten().sum();
+            assert_eq!(13, sum);
+            assert!(dest_port.iter_recv::<u32>(&mut buf).next().is_none());
+        })
+    }
+
     #[cfg(feature = "alloc")]
     #[test]
     fn queuing_type() {
@@ -300,7 +1285,22 @@ mod tests {
                 .unwrap();
             let (rec, _): (String, _) = dest_port.recv_type(SystemTime::Infinite).unwrap();
 
-            assert_eq!(msg, rec)
+            assert_eq!(msg, rec);
+
+            // Values too large for the port fail alike with and without a codec
+            let large = "x".repeat(501);
+            assert!(matches!(
+                src_port.send_type(&large, SystemTime::Infinite),
+                Err(crate::error::SendError::Postcard(
+                    postcard::Error::SerializeBufferFull
+                ))
+            ));
+            assert!(matches!(
+                src_port.send_type_with(&crate::codec::Postcard, &large, SystemTime::Infinite),
+                Err(crate::error::SendError::Postcard(
+                    postcard::Error::SerializeBufferFull
+                ))
+            ));
         })
     }
 
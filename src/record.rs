@@ -0,0 +1,1209 @@
+//! Capture and replay of port traffic
+//!
+//! [`RecordingHyp`] wraps a hypervisor, logging every message sent or received by its
+//! ports as a [`Record`], so that all extension traits of this crate are recorded unchanged.
+//! Recordings are streams of COBS-framed postcard records.
+//! [`ReplayHyp`] is a hypervisor feeding a recording back into the receiving ports,
+//! reproducing the received messages deterministically, e.g. on a bench.
+
+extern crate std;
+
+use core::marker::PhantomData;
+use core::time::Duration;
+use std::boxed::Box;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::string::String;
+use std::sync::{Mutex, MutexGuard};
+use std::vec::Vec;
+
+use a653rs::bindings::*;
+use a653rs::prelude::{
+    Error, Name, QueuingPortReceiver, QueuingPortSender, SamplingPortDestination,
+    SamplingPortSource, SystemTime,
+};
+use serde::{Deserialize, Serialize};
+
+/// Traffic of a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    /// The message was sent
+    Sent,
+    /// The message was received from a queuing port
+    Received { overflow: QueueOverflow },
+    /// The message was read from a sampling port
+    Sampled { valid: bool },
+}
+
+/// Single message sent or received by a port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Port name
+    pub port: String,
+    /// System time of the hypervisor in nanoseconds
+    pub timestamp: ApexSystemTime,
+    /// Whether the message was sent or received
+    pub event: Event,
+    /// Raw message
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Append the record to a recording
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let frame = postcard::to_allocvec_cobs(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        writer.write_all(&frame)
+    }
+}
+
+/// Read all records of a recording
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if any record is malformed.
+pub fn read_recording(mut reader: impl Read) -> io::Result<Vec<Record>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    bytes
+        .split_mut(|b| *b == 0)
+        .filter(|frame| !frame.is_empty())
+        .map(|frame| {
+            postcard::from_bytes_cobs(frame)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Name of a port, without trailing zero bytes
+fn port_name(name: &[u8]) -> String {
+    name.iter()
+        .take_while(|b| **b != 0)
+        .map(|b| *b as char)
+        .collect()
+}
+
+/// Whether a port is a queuing or a sampling port, and its identifier
+type PortKey = (bool, ApexLongInteger);
+
+struct Recorder {
+    writer: Option<Box<dyn Write + Send>>,
+    error: Option<io::Error>,
+    ports: Vec<(PortKey, String)>,
+}
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder {
+    writer: None,
+    error: None,
+    ports: Vec::new(),
+});
+
+fn recorder() -> MutexGuard<'static, Recorder> {
+    RECORDER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Recorder {
+    fn register(&mut self, key: PortKey, name: &[u8]) {
+        self.ports.retain(|(k, _)| *k != key);
+        self.ports.push((key, port_name(name)));
+    }
+
+    fn record(&mut self, key: PortKey, timestamp: ApexSystemTime, event: Event, data: &[u8]) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        if self.error.is_some() {
+            return;
+        }
+        let port = self
+            .ports
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_default();
+        let record = Record {
+            port,
+            timestamp,
+            event,
+            data: data.to_vec(),
+        };
+        self.error = record.write(writer).err();
+    }
+}
+
+/// Hypervisor recording the traffic of the ports of hypervisor `H`
+///
+/// Partitions are run with `RecordingHyp<H>` as their hypervisor,
+/// e.g. using [`PartitionExt::run`](a653rs::prelude::PartitionExt::run),
+/// so that every port created by their start context is recorded.
+/// Only one recording is active per process, shared by all ports created with any `H`.
+/// Only successfully sent and received messages are recorded.
+/// All other services of `H`, e.g. processes, buffers or blackboards, are forwarded unchanged.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// # Hypervisor::run_test_as::<RecordingHyp<Hypervisor>>(|mut ctx| {
+/// let file = std::env::temp_dir().join("a653rs-postcard-example.rec");
+/// RecordingHyp::<Hypervisor>::start(std::fs::File::create(&file).unwrap());
+///
+/// // Start context of a partition run with `RecordingHyp<Hypervisor>`
+/// let port = ctx
+///     .create_queuing_port_sender(Name::from_str("CMD").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+///     .unwrap();
+/// let mut buf = [0; 16];
+/// port.send_type_buf(42u32, SystemTime::Infinite, &mut buf).unwrap();
+///
+/// RecordingHyp::<Hypervisor>::finish().unwrap();
+/// let recording = read_recording(std::fs::File::open(&file).unwrap()).unwrap();
+/// assert_eq!("CMD", recording[0].port);
+/// assert_eq!(Event::Sent, recording[0].event);
+/// # })
+/// ```
+pub struct RecordingHyp<H>(PhantomData<H>);
+
+impl<H> RecordingHyp<H> {
+    /// Start recording to `writer`, replacing any active recording
+    pub fn start(writer: impl Write + Send + 'static) {
+        let mut recorder = recorder();
+        recorder.writer = Some(Box::new(writer));
+        recorder.error = None;
+    }
+
+    /// Stop recording, flushing the writer
+    ///
+    /// Returns the first error of writing any record.
+    pub fn finish() -> io::Result<()> {
+        let mut recorder = recorder();
+        let error = recorder.error.take();
+        match recorder.writer.take() {
+            Some(mut writer) => error.map_or_else(|| writer.flush(), Err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<H: ApexPartitionP4> ApexPartitionP4 for RecordingHyp<H> {
+    fn get_partition_status() -> ApexPartitionStatus {
+        H::get_partition_status()
+    }
+
+    fn set_partition_mode(operating_mode: OperatingMode) -> Result<(), ErrorReturnCode> {
+        H::set_partition_mode(operating_mode)
+    }
+}
+
+impl<H: ApexProcessP4> ApexProcessP4 for RecordingHyp<H> {
+    fn create_process(attributes: &ApexProcessAttribute) -> Result<ProcessId, ErrorReturnCode> {
+        H::create_process(attributes)
+    }
+
+    fn start(process_id: ProcessId) -> Result<(), ErrorReturnCode> {
+        H::start(process_id)
+    }
+}
+impl<H: ApexProcessP1> ApexProcessP1 for RecordingHyp<H> {
+    fn set_priority(process_id: ProcessId, priority: Priority) -> Result<(), ErrorReturnCode> {
+        H::set_priority(process_id, priority)
+    }
+
+    fn suspend_self(time_out: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        H::suspend_self(time_out)
+    }
+
+    fn suspend(process_id: ProcessId) -> Result<(), ErrorReturnCode> {
+        H::suspend(process_id)
+    }
+
+    fn resume(process_id: ProcessId) -> Result<(), ErrorReturnCode> {
+        H::resume(process_id)
+    }
+
+    fn stop_self() {
+        H::stop_self()
+    }
+
+    fn stop(process_id: ProcessId) -> Result<(), ErrorReturnCode> {
+        H::stop(process_id)
+    }
+
+    fn delayed_start(
+        process_id: ProcessId,
+        delay_time: ApexSystemTime,
+    ) -> Result<(), ErrorReturnCode> {
+        H::delayed_start(process_id, delay_time)
+    }
+
+    fn lock_preemption() -> Result<LockLevel, ErrorReturnCode> {
+        H::lock_preemption()
+    }
+
+    fn unlock_preemption() -> Result<LockLevel, ErrorReturnCode> {
+        H::unlock_preemption()
+    }
+
+    fn get_my_id() -> Result<ProcessId, ErrorReturnCode> {
+        H::get_my_id()
+    }
+
+    fn get_process_id(process_name: ProcessName) -> Result<ProcessId, ErrorReturnCode> {
+        H::get_process_id(process_name)
+    }
+
+    fn get_process_status(process_id: ProcessId) -> Result<ApexProcessStatus, ErrorReturnCode> {
+        H::get_process_status(process_id)
+    }
+
+    fn initialize_process_core_affinity(
+        process_id: ProcessId,
+        processor_core_id: ProcessorCoreId,
+    ) -> Result<(), ErrorReturnCode> {
+        H::initialize_process_core_affinity(process_id, processor_core_id)
+    }
+
+    fn get_my_processor_core_id() -> ProcessorCoreId {
+        H::get_my_processor_core_id()
+    }
+
+    fn get_my_index() -> Result<ProcessIndex, ErrorReturnCode> {
+        H::get_my_index()
+    }
+}
+
+impl<H: ApexErrorP4> ApexErrorP4 for RecordingHyp<H> {
+    fn report_application_message(message: &[ApexByte]) -> Result<(), ErrorReturnCode> {
+        H::report_application_message(message)
+    }
+
+    fn raise_application_error(
+        error_code: ErrorCode,
+        message: &[ApexByte],
+    ) -> Result<(), ErrorReturnCode> {
+        H::raise_application_error(error_code, message)
+    }
+}
+
+impl<H: ApexErrorP1> ApexErrorP1 for RecordingHyp<H> {
+    fn create_error_handler(
+        entry_point: SystemAddress,
+        stack_size: StackSize,
+    ) -> Result<(), ErrorReturnCode> {
+        H::create_error_handler(entry_point, stack_size)
+    }
+
+    fn get_error_status() -> Result<ErrorStatus, ErrorReturnCode> {
+        H::get_error_status()
+    }
+
+    fn configure_error_handler(
+        concurrency_control: ErrorHandlerConcurrencyControl,
+        processor_core_id: ProcessorCoreId,
+    ) -> Result<(), ErrorReturnCode> {
+        H::configure_error_handler(concurrency_control, processor_core_id)
+    }
+}
+
+impl<H: ApexQueuingPortP4 + ApexTimeP4> ApexQueuingPortP4 for RecordingHyp<H> {
+    fn create_queuing_port(
+        queuing_port_name: QueuingPortName,
+        max_message_size: MessageSize,
+        max_nb_message: MessageRange,
+        port_direction: PortDirection,
+        queuing_discipline: QueuingDiscipline,
+    ) -> Result<QueuingPortId, ErrorReturnCode> {
+        let id = H::create_queuing_port(
+            queuing_port_name,
+            max_message_size,
+            max_nb_message,
+            port_direction,
+            queuing_discipline,
+        )?;
+        recorder().register((true, id), &queuing_port_name);
+        Ok(id)
+    }
+
+    fn send_queuing_message(
+        queuing_port_id: QueuingPortId,
+        message: &[ApexByte],
+        time_out: ApexSystemTime,
+    ) -> Result<(), ErrorReturnCode> {
+        H::send_queuing_message(queuing_port_id, message, time_out)?;
+        recorder().record((true, queuing_port_id), H::get_time(), Event::Sent, message);
+        Ok(())
+    }
+
+    unsafe fn receive_queuing_message(
+        queuing_port_id: QueuingPortId,
+        time_out: ApexSystemTime,
+        message: &mut [ApexByte],
+    ) -> Result<(MessageSize, QueueOverflow), ErrorReturnCode> {
+        let (len, overflow) = H::receive_queuing_message(queuing_port_id, time_out, message)?;
+        recorder().record(
+            (true, queuing_port_id),
+            H::get_time(),
+            Event::Received { overflow },
+            &message[..len as usize],
+        );
+        Ok((len, overflow))
+    }
+
+    fn get_queuing_port_status(
+        queuing_port_id: QueuingPortId,
+    ) -> Result<QueuingPortStatus, ErrorReturnCode> {
+        H::get_queuing_port_status(queuing_port_id)
+    }
+
+    fn clear_queuing_port(queuing_port_id: QueuingPortId) -> Result<(), ErrorReturnCode> {
+        H::clear_queuing_port(queuing_port_id)
+    }
+}
+impl<H: ApexQueuingPortP1 + ApexTimeP4> ApexQueuingPortP1 for RecordingHyp<H> {
+    fn get_queuing_port_id(
+        queuing_port_name: QueuingPortName,
+    ) -> Result<QueuingPortId, ErrorReturnCode> {
+        H::get_queuing_port_id(queuing_port_name)
+    }
+}
+
+impl<H: ApexSamplingPortP4 + ApexTimeP4> ApexSamplingPortP4 for RecordingHyp<H> {
+    fn create_sampling_port(
+        sampling_port_name: SamplingPortName,
+        max_message_size: MessageSize,
+        port_direction: PortDirection,
+        refresh_period: ApexSystemTime,
+    ) -> Result<SamplingPortId, ErrorReturnCode> {
+        let id = H::create_sampling_port(
+            sampling_port_name,
+            max_message_size,
+            port_direction,
+            refresh_period,
+        )?;
+        recorder().register((false, id), &sampling_port_name);
+        Ok(id)
+    }
+
+    fn write_sampling_message(
+        sampling_port_id: SamplingPortId,
+        message: &[ApexByte],
+    ) -> Result<(), ErrorReturnCode> {
+        H::write_sampling_message(sampling_port_id, message)?;
+        recorder().record(
+            (false, sampling_port_id),
+            H::get_time(),
+            Event::Sent,
+            message,
+        );
+        Ok(())
+    }
+
+    unsafe fn read_sampling_message(
+        sampling_port_id: SamplingPortId,
+        message: &mut [ApexByte],
+    ) -> Result<(Validity, MessageSize), ErrorReturnCode> {
+        let (validity, len) = H::read_sampling_message(sampling_port_id, message)?;
+        let event = Event::Sampled {
+            valid: validity == Validity::Valid,
+        };
+        recorder().record(
+            (false, sampling_port_id),
+            H::get_time(),
+            event,
+            &message[..len as usize],
+        );
+        Ok((validity, len))
+    }
+}
+impl<H: ApexSamplingPortP1 + ApexTimeP4> ApexSamplingPortP1 for RecordingHyp<H> {
+    fn get_sampling_port_id(
+        sampling_port_name: SamplingPortName,
+    ) -> Result<SamplingPortId, ErrorReturnCode> {
+        H::get_sampling_port_id(sampling_port_name)
+    }
+
+    fn get_sampling_port_status(
+        sampling_port_id: SamplingPortId,
+    ) -> Result<ApexSamplingPortStatus, ErrorReturnCode> {
+        H::get_sampling_port_status(sampling_port_id)
+    }
+}
+
+impl<H: ApexTimeP4> ApexTimeP4 for RecordingHyp<H> {
+    fn periodic_wait() -> Result<(), ErrorReturnCode> {
+        H::periodic_wait()
+    }
+
+    fn get_time() -> ApexSystemTime {
+        H::get_time()
+    }
+}
+
+impl<H: ApexTimeP1> ApexTimeP1 for RecordingHyp<H> {
+    fn timed_wait(delay_time: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        H::timed_wait(delay_time)
+    }
+
+    fn replenish(budget_time: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        H::replenish(budget_time)
+    }
+}
+impl<H: ApexBufferP1> ApexBufferP1 for RecordingHyp<H> {
+    fn create_buffer(
+        buffer_name: BufferName,
+        max_message_size: MessageSize,
+        max_nb_message: MessageRange,
+        queuing_discipline: QueuingDiscipline,
+    ) -> Result<BufferId, ErrorReturnCode> {
+        H::create_buffer(
+            buffer_name,
+            max_message_size,
+            max_nb_message,
+            queuing_discipline,
+        )
+    }
+
+    fn send_buffer(
+        buffer_id: BufferId,
+        message: &[ApexByte],
+        time_out: ApexSystemTime,
+    ) -> Result<(), ErrorReturnCode> {
+        H::send_buffer(buffer_id, message, time_out)
+    }
+
+    unsafe fn receive_buffer(
+        buffer_id: BufferId,
+        time_out: ApexSystemTime,
+        message: &mut [ApexByte],
+    ) -> Result<MessageSize, ErrorReturnCode> {
+        H::receive_buffer(buffer_id, time_out, message)
+    }
+
+    fn get_buffer_id(buffer_name: BufferName) -> Result<BufferId, ErrorReturnCode> {
+        H::get_buffer_id(buffer_name)
+    }
+
+    fn get_buffer_status(buffer_id: BufferId) -> Result<BufferStatus, ErrorReturnCode> {
+        H::get_buffer_status(buffer_id)
+    }
+}
+
+impl<H: ApexBlackboardP1> ApexBlackboardP1 for RecordingHyp<H> {
+    fn create_blackboard(
+        blackboard_name: BlackboardName,
+        max_message_size: MessageSize,
+    ) -> Result<BlackboardId, ErrorReturnCode> {
+        H::create_blackboard(blackboard_name, max_message_size)
+    }
+
+    fn display_blackboard(
+        blackboard_id: BlackboardId,
+        message: &[ApexByte],
+    ) -> Result<(), ErrorReturnCode> {
+        H::display_blackboard(blackboard_id, message)
+    }
+
+    unsafe fn read_blackboard(
+        blackboard_id: BlackboardId,
+        time_out: ApexSystemTime,
+        message: &mut [ApexByte],
+    ) -> Result<MessageSize, ErrorReturnCode> {
+        H::read_blackboard(blackboard_id, time_out, message)
+    }
+
+    fn clear_blackboard(blackboard_id: BlackboardId) -> Result<(), ErrorReturnCode> {
+        H::clear_blackboard(blackboard_id)
+    }
+
+    fn get_blackboard_id(blackboard_name: BlackboardName) -> Result<BlackboardId, ErrorReturnCode> {
+        H::get_blackboard_id(blackboard_name)
+    }
+
+    fn get_blackboard_status(
+        blackboard_id: BlackboardId,
+    ) -> Result<BlackboardStatus, ErrorReturnCode> {
+        H::get_blackboard_status(blackboard_id)
+    }
+}
+
+impl<H: ApexSemaphoreP1> ApexSemaphoreP1 for RecordingHyp<H> {
+    fn create_semaphore(
+        semaphore_name: SemaphoreName,
+        current_value: SemaphoreValue,
+        maximum_value: SemaphoreValue,
+        queuing_discipline: QueuingDiscipline,
+    ) -> Result<SemaphoreId, ErrorReturnCode> {
+        H::create_semaphore(
+            semaphore_name,
+            current_value,
+            maximum_value,
+            queuing_discipline,
+        )
+    }
+
+    fn wait_semaphore(
+        semaphore_id: SemaphoreId,
+        time_out: ApexSystemTime,
+    ) -> Result<(), ErrorReturnCode> {
+        H::wait_semaphore(semaphore_id, time_out)
+    }
+
+    fn signal_semaphore(semaphore_id: SemaphoreId) -> Result<(), ErrorReturnCode> {
+        H::signal_semaphore(semaphore_id)
+    }
+
+    fn get_semaphore_id(semaphore_name: SemaphoreName) -> Result<SemaphoreId, ErrorReturnCode> {
+        H::get_semaphore_id(semaphore_name)
+    }
+
+    fn get_semaphore_status(semaphore_id: SemaphoreId) -> Result<SemaphoreStatus, ErrorReturnCode> {
+        H::get_semaphore_status(semaphore_id)
+    }
+}
+
+impl<H: ApexEventP1> ApexEventP1 for RecordingHyp<H> {
+    fn create_event(event_name: EventName) -> Result<EventId, ErrorReturnCode> {
+        H::create_event(event_name)
+    }
+
+    fn set_event(event_id: EventId) -> Result<(), ErrorReturnCode> {
+        H::set_event(event_id)
+    }
+
+    fn reset_event(event_id: EventId) -> Result<(), ErrorReturnCode> {
+        H::reset_event(event_id)
+    }
+
+    fn wait_event(event_id: EventId, time_out: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        H::wait_event(event_id, time_out)
+    }
+
+    fn get_event_id(event_name: EventName) -> Result<EventId, ErrorReturnCode> {
+        H::get_event_id(event_name)
+    }
+
+    fn get_event_status(event_id: EventId) -> Result<EventStatus, ErrorReturnCode> {
+        H::get_event_status(event_id)
+    }
+}
+
+impl<H: ApexMutexP1> ApexMutexP1 for RecordingHyp<H> {
+    fn create_mutex(
+        mutex_name: MutexName,
+        mutex_priority: Priority,
+        queuing_discipline: QueuingDiscipline,
+    ) -> Result<MutexId, ErrorReturnCode> {
+        H::create_mutex(mutex_name, mutex_priority, queuing_discipline)
+    }
+
+    fn acquire_mutex(mutex_id: MutexId, time_out: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        H::acquire_mutex(mutex_id, time_out)
+    }
+
+    fn release_mutex(mutex_id: MutexId) -> Result<(), ErrorReturnCode> {
+        H::release_mutex(mutex_id)
+    }
+
+    fn reset_mutex(mutex_id: MutexId, process_id: ProcessId) -> Result<(), ErrorReturnCode> {
+        H::reset_mutex(mutex_id, process_id)
+    }
+
+    fn get_mutex_id(mutex_name: MutexName) -> Result<MutexId, ErrorReturnCode> {
+        H::get_mutex_id(mutex_name)
+    }
+
+    fn get_mutex_status(mutex_id: MutexId) -> Result<MutexStatus, ErrorReturnCode> {
+        H::get_mutex_status(mutex_id)
+    }
+
+    fn get_process_mutex_state(process_id: ProcessId) -> Result<MutexId, ErrorReturnCode> {
+        H::get_process_mutex_state(process_id)
+    }
+}
+
+impl<H: ApexMemoryBlockP2> ApexMemoryBlockP2 for RecordingHyp<H> {
+    fn get_memory_block_status(
+        memory_block_name: MemoryBlockName,
+    ) -> Result<ApexMemoryBlockStatus, ErrorReturnCode> {
+        H::get_memory_block_status(memory_block_name)
+    }
+}
+
+impl<H: ApexScheduleP2> ApexScheduleP2 for RecordingHyp<H> {
+    fn set_module_schedule(schedule_id: ScheduleId) -> Result<(), ErrorReturnCode> {
+        H::set_module_schedule(schedule_id)
+    }
+
+    fn get_module_schedule_status() -> Result<ApexScheduleStatus, ErrorReturnCode> {
+        H::get_module_schedule_status()
+    }
+
+    fn get_module_schedule_id(schedule_name: ScheduleName) -> Result<ScheduleId, ErrorReturnCode> {
+        H::get_module_schedule_id(schedule_name)
+    }
+}
+
+impl<H: ApexLimits> ApexLimits for RecordingHyp<H> {
+    const SYSTEM_LIMIT_NUMBER_OF_PARTITIONS: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_PARTITIONS;
+    const SYSTEM_LIMIT_NUMBER_OF_MESSAGES: MessageRange = H::SYSTEM_LIMIT_NUMBER_OF_MESSAGES;
+    const SYSTEM_LIMIT_MESSAGE_SIZE: MessageSize = H::SYSTEM_LIMIT_MESSAGE_SIZE;
+    const SYSTEM_LIMIT_NUMBER_OF_PROCESSES: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_PROCESSES;
+    const SYSTEM_LIMIT_NUMBER_OF_SAMPLING_PORTS: ApexUnsigned =
+        H::SYSTEM_LIMIT_NUMBER_OF_SAMPLING_PORTS;
+    const SYSTEM_LIMIT_NUMBER_OF_QUEUING_PORTS: ApexUnsigned =
+        H::SYSTEM_LIMIT_NUMBER_OF_QUEUING_PORTS;
+    const SYSTEM_LIMIT_NUMBER_OF_BUFFERS: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_BUFFERS;
+    const SYSTEM_LIMIT_NUMBER_OF_BLACKBOARDS: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_BLACKBOARDS;
+    const SYSTEM_LIMIT_NUMBER_OF_SEMAPHORES: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_SEMAPHORES;
+    const SYSTEM_LIMIT_NUMBER_OF_EVENTS: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_EVENTS;
+    const SYSTEM_LIMIT_NUMBER_OF_MUTEXES: ApexUnsigned = H::SYSTEM_LIMIT_NUMBER_OF_MUTEXES;
+}
+
+/// Port created during a replay
+struct ReplayPort {
+    name: String,
+    queuing: bool,
+    size: MessageSize,
+    depth: MessageRange,
+    direction: PortDirection,
+    refresh: ApexSystemTime,
+}
+
+struct Replay {
+    /// Created ports, indexed by their identifier
+    ports: Vec<ReplayPort>,
+    /// Received messages of a recording not yet replayed
+    pending: VecDeque<Record>,
+    /// Messages sent during the replay
+    sent: Vec<Record>,
+    time: ApexSystemTime,
+}
+
+static REPLAY: Mutex<Replay> = Mutex::new(Replay {
+    ports: Vec::new(),
+    pending: VecDeque::new(),
+    sent: Vec::new(),
+    time: 0,
+});
+static REPLAY_SYNC: Mutex<()> = Mutex::new(());
+
+fn replay() -> MutexGuard<'static, Replay> {
+    REPLAY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Replay {
+    fn create(&mut self, port: ReplayPort) -> ApexLongInteger {
+        self.ports.push(port);
+        self.ports.len() as ApexLongInteger - 1
+    }
+
+    fn port(&self, id: ApexLongInteger) -> Result<&ReplayPort, ErrorReturnCode> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|id| self.ports.get(id))
+            .ok_or(ErrorReturnCode::InvalidParam)
+    }
+
+    /// Identifier of the latest created port called `name`
+    fn id(&self, name: &[u8], queuing: bool) -> Result<ApexLongInteger, ErrorReturnCode> {
+        let name = port_name(name);
+        self.ports
+            .iter()
+            .rposition(|p| p.name == name && p.queuing == queuing)
+            .map(|id| id as ApexLongInteger)
+            .ok_or(ErrorReturnCode::InvalidConfig)
+    }
+
+    fn send(&mut self, id: ApexLongInteger, data: &[u8]) -> Result<(), ErrorReturnCode> {
+        let port = self.port(id)?.name.clone();
+        self.sent.push(Record {
+            port,
+            timestamp: self.time,
+            event: Event::Sent,
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Whether `record` is a message received by a port of kind `queuing`
+    fn is_received(record: &Record, port: &ReplayPort) -> bool {
+        record.port == port.name && matches!(record.event, Event::Received { .. }) == port.queuing
+    }
+
+    /// Take the next recorded message received by the port
+    ///
+    /// Fails with [`ErrorReturnCode::InvalidConfig`] if the message is larger than the port.
+    fn receive(
+        &mut self,
+        id: ApexLongInteger,
+        out: &mut [u8],
+    ) -> Result<(Event, MessageSize), ErrorReturnCode> {
+        let port = self.port(id)?;
+        let (queuing, size) = (port.queuing, port.size as usize);
+        let index = self.pending.iter().position(|r| Self::is_received(r, port));
+        let Some(record) = index.and_then(|i| self.pending.remove(i)) else {
+            return Err(match queuing {
+                true => ErrorReturnCode::NotAvailable,
+                false => ErrorReturnCode::NoAction,
+            });
+        };
+        self.time = self.time.max(record.timestamp);
+        let len = record.data.len();
+        let out = out
+            .get_mut(..len)
+            .filter(|_| len <= size)
+            .ok_or(ErrorReturnCode::InvalidConfig)?;
+        out.copy_from_slice(&record.data);
+        Ok((record.event, len as MessageSize))
+    }
+}
+
+/// Context creating the ports of a replay, like the start context of a partition
+#[derive(Debug)]
+pub struct ReplayContext(());
+
+impl ReplayContext {
+    /// Create a queuing port sending messages collected by the replay
+    pub fn create_queuing_port_sender(
+        &mut self,
+        name: Name,
+        msg_size: MessageSize,
+        nb_msgs: MessageRange,
+        qd: QueuingDiscipline,
+    ) -> Result<QueuingPortSender<ReplayHyp>, Error> {
+        <ReplayHyp as ApexQueuingPortP4>::create_queuing_port(
+            name.clone().into(),
+            msg_size,
+            nb_msgs,
+            PortDirection::Source,
+            qd,
+        )?;
+        QueuingPortSender::from_name(name)
+    }
+
+    /// Create a queuing port receiving the recorded messages of the port called `name`
+    pub fn create_queuing_port_receiver(
+        &mut self,
+        name: Name,
+        msg_size: MessageSize,
+        nb_msgs: MessageRange,
+        qd: QueuingDiscipline,
+    ) -> Result<QueuingPortReceiver<ReplayHyp>, Error> {
+        <ReplayHyp as ApexQueuingPortP4>::create_queuing_port(
+            name.clone().into(),
+            msg_size,
+            nb_msgs,
+            PortDirection::Destination,
+            qd,
+        )?;
+        QueuingPortReceiver::from_name(name)
+    }
+
+    /// Create a sampling port sending messages collected by the replay
+    pub fn create_sampling_port_source(
+        &mut self,
+        name: Name,
+        msg_size: MessageSize,
+    ) -> Result<SamplingPortSource<ReplayHyp>, Error> {
+        <ReplayHyp as ApexSamplingPortP4>::create_sampling_port(
+            name.clone().into(),
+            msg_size,
+            PortDirection::Source,
+            SystemTime::Normal(Duration::from_nanos(1)).into(),
+        )?;
+        SamplingPortSource::from_name(name)
+    }
+
+    /// Create a sampling port reading the recorded messages of the port called `name`
+    pub fn create_sampling_port_destination(
+        &mut self,
+        name: Name,
+        msg_size: MessageSize,
+        refresh: Duration,
+    ) -> Result<SamplingPortDestination<ReplayHyp>, Error> {
+        <ReplayHyp as ApexSamplingPortP4>::create_sampling_port(
+            name.clone().into(),
+            msg_size,
+            PortDirection::Destination,
+            SystemTime::Normal(refresh).into(),
+        )?;
+        SamplingPortDestination::from_name(name)
+    }
+}
+
+/// Hypervisor replaying a recording into the receiving ports
+///
+/// Every port receives the messages recorded for a port of the same name and kind
+/// in their recorded order, regardless of timing,
+/// and the system time advances to the timestamp of the latest received message.
+/// Once a port has received all its messages, it reports no message available.
+/// Recorded messages larger than the port fail with [`Error::InvalidConfig`].
+/// Sent messages are collected instead of being delivered.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// let recording = vec![Record {
+///     port: String::from("CMD"),
+///     timestamp: 1_000_000,
+///     event: Event::Received { overflow: false },
+///     data: vec![42],
+/// }];
+///
+/// let (value, sent) = ReplayHyp::replay(recording, |mut ctx| {
+///     let port = ctx
+///         .create_queuing_port_receiver(Name::from_str("CMD").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+///         .unwrap();
+///     let mut buf = [0; 16];
+///     let (value, _) = port.recv_type_buf::<u8>(SystemTime::Infinite, &mut buf).unwrap();
+///     value
+/// });
+/// assert_eq!(42, value);
+/// assert!(sent.is_empty());
+/// ```
+pub struct ReplayHyp;
+
+impl ReplayHyp {
+    /// Run `f` with the messages of `recording` available for receiving
+    ///
+    /// Replays are run one at a time.
+    /// Returns the result of `f` and all messages sent during the replay.
+    pub fn replay<R>(
+        recording: impl IntoIterator<Item = Record>,
+        f: impl FnOnce(ReplayContext) -> R,
+    ) -> (R, Vec<Record>) {
+        let _sync = REPLAY_SYNC.lock().unwrap_or_else(|e| e.into_inner());
+        {
+            let mut replay = replay();
+            replay.ports.clear();
+            replay.sent.clear();
+            replay.time = 0;
+            replay.pending = recording
+                .into_iter()
+                .filter(|r| r.event != Event::Sent)
+                .collect();
+        }
+        let result = f(ReplayContext(()));
+        let sent = core::mem::take(&mut replay().sent);
+        (result, sent)
+    }
+}
+
+impl ApexQueuingPortP4 for ReplayHyp {
+    fn create_queuing_port(
+        queuing_port_name: QueuingPortName,
+        max_message_size: MessageSize,
+        max_nb_message: MessageRange,
+        port_direction: PortDirection,
+        _queuing_discipline: QueuingDiscipline,
+    ) -> Result<QueuingPortId, ErrorReturnCode> {
+        Ok(replay().create(ReplayPort {
+            name: port_name(&queuing_port_name),
+            queuing: true,
+            size: max_message_size,
+            depth: max_nb_message,
+            direction: port_direction,
+            refresh: 0,
+        }))
+    }
+
+    fn send_queuing_message(
+        queuing_port_id: QueuingPortId,
+        message: &[ApexByte],
+        _time_out: ApexSystemTime,
+    ) -> Result<(), ErrorReturnCode> {
+        replay().send(queuing_port_id, message)
+    }
+
+    unsafe fn receive_queuing_message(
+        queuing_port_id: QueuingPortId,
+        _time_out: ApexSystemTime,
+        message: &mut [ApexByte],
+    ) -> Result<(MessageSize, QueueOverflow), ErrorReturnCode> {
+        match replay().receive(queuing_port_id, message)? {
+            (Event::Received { overflow }, len) => Ok((len, overflow)),
+            (_, len) => Ok((len, false)),
+        }
+    }
+
+    fn get_queuing_port_status(
+        queuing_port_id: QueuingPortId,
+    ) -> Result<QueuingPortStatus, ErrorReturnCode> {
+        let replay = replay();
+        let port = replay.port(queuing_port_id)?;
+        let pending = replay
+            .pending
+            .iter()
+            .filter(|r| Replay::is_received(r, port))
+            .count();
+        Ok(QueuingPortStatus {
+            nb_message: pending.min(port.depth as usize) as MessageRange,
+            max_nb_message: port.depth,
+            max_message_size: port.size,
+            port_direction: port.direction,
+            waiting_processes: 0,
+        })
+    }
+
+    fn clear_queuing_port(queuing_port_id: QueuingPortId) -> Result<(), ErrorReturnCode> {
+        let mut replay = replay();
+        let Replay { ports, pending, .. } = &mut *replay;
+        let port = usize::try_from(queuing_port_id)
+            .ok()
+            .and_then(|id| ports.get(id))
+            .ok_or(ErrorReturnCode::InvalidParam)?;
+        pending.retain(|r| !Replay::is_received(r, port));
+        Ok(())
+    }
+}
+
+impl ApexQueuingPortP1 for ReplayHyp {
+    fn get_queuing_port_id(
+        queuing_port_name: QueuingPortName,
+    ) -> Result<QueuingPortId, ErrorReturnCode> {
+        replay().id(&queuing_port_name, true)
+    }
+}
+
+impl ApexSamplingPortP4 for ReplayHyp {
+    fn create_sampling_port(
+        sampling_port_name: SamplingPortName,
+        max_message_size: MessageSize,
+        port_direction: PortDirection,
+        refresh_period: ApexSystemTime,
+    ) -> Result<SamplingPortId, ErrorReturnCode> {
+        Ok(replay().create(ReplayPort {
+            name: port_name(&sampling_port_name),
+            queuing: false,
+            size: max_message_size,
+            depth: 0,
+            direction: port_direction,
+            refresh: refresh_period,
+        }))
+    }
+
+    fn write_sampling_message(
+        sampling_port_id: SamplingPortId,
+        message: &[ApexByte],
+    ) -> Result<(), ErrorReturnCode> {
+        replay().send(sampling_port_id, message)
+    }
+
+    unsafe fn read_sampling_message(
+        sampling_port_id: SamplingPortId,
+        message: &mut [ApexByte],
+    ) -> Result<(Validity, MessageSize), ErrorReturnCode> {
+        match replay().receive(sampling_port_id, message)? {
+            (Event::Sampled { valid: false }, len) => Ok((Validity::Invalid, len)),
+            (_, len) => Ok((Validity::Valid, len)),
+        }
+    }
+}
+
+impl ApexSamplingPortP1 for ReplayHyp {
+    fn get_sampling_port_id(
+        sampling_port_name: SamplingPortName,
+    ) -> Result<SamplingPortId, ErrorReturnCode> {
+        replay().id(&sampling_port_name, false)
+    }
+
+    fn get_sampling_port_status(
+        sampling_port_id: SamplingPortId,
+    ) -> Result<ApexSamplingPortStatus, ErrorReturnCode> {
+        let replay = replay();
+        let port = replay.port(sampling_port_id)?;
+        Ok(ApexSamplingPortStatus {
+            refresh_period: port.refresh,
+            max_message_size: port.size,
+            port_direction: port.direction,
+            last_msg_validity: Validity::Valid,
+        })
+    }
+}
+
+impl ApexTimeP4 for ReplayHyp {
+    fn periodic_wait() -> Result<(), ErrorReturnCode> {
+        Ok(())
+    }
+
+    fn get_time() -> ApexSystemTime {
+        replay().time
+    }
+}
+
+impl ApexTimeP1 for ReplayHyp {
+    fn timed_wait(delay_time: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        replay().time += delay_time.max(0);
+        Ok(())
+    }
+
+    fn replenish(_budget_time: ApexSystemTime) -> Result<(), ErrorReturnCode> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+    use std::vec;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Error, Name, SystemTime, Validity};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    static FILE: std::sync::Mutex<std::vec::Vec<u8>> = std::sync::Mutex::new(std::vec::Vec::new());
+
+    /// Writer appending to [`FILE`]
+    struct File;
+
+    impl std::io::Write for File {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            FILE.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        MockHyp::run_test_as::<RecordingHyp<MockHyp>>(|mut ctx| {
+            RecordingHyp::<MockHyp>::start(File);
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("Q").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("Q").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let sampling_src = ctx
+                .create_sampling_port_source(Name::from_str("S").unwrap(), 16)
+                .unwrap();
+            let sampling_dest = ctx
+                .create_sampling_port_destination(Name::from_str("S").unwrap(), 16, Duration::ZERO)
+                .unwrap();
+            let mut buf = [0; 16];
+
+            for value in [1u32, 2] {
+                src_port
+                    .send_type_buf(value, SystemTime::Infinite, &mut buf)
+                    .unwrap();
+            }
+            sampling_src.send_type_buf(3u32, &mut buf).unwrap();
+            MockHyp::advance(Duration::from_millis(5));
+            dest_port
+                .recv_type_buf::<u32>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            MockHyp::set_validity(sampling_dest.id(), Validity::Invalid);
+            sampling_dest.recv_type_buf::<u32>(&mut buf).unwrap();
+            MockHyp::advance(Duration::from_millis(5));
+            dest_port
+                .recv_type_buf::<u32>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            RecordingHyp::<MockHyp>::finish().unwrap();
+        });
+
+        let bytes = FILE.lock().unwrap().clone();
+        let recording = read_recording(bytes.as_slice()).unwrap();
+        let events: std::vec::Vec<_> = recording
+            .iter()
+            .map(|r| (r.port.as_str(), r.event))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Q", Event::Sent),
+                ("Q", Event::Sent),
+                ("S", Event::Sent),
+                ("Q", Event::Received { overflow: false }),
+                ("S", Event::Sampled { valid: false }),
+                ("Q", Event::Received { overflow: false }),
+            ],
+            events
+        );
+        assert_eq!(10_000_000, recording[5].timestamp);
+        assert!(read_recording([1u8, 2, 3, 0].as_slice()).is_err());
+
+        let (received, sent) = ReplayHyp::replay(recording, |mut ctx| {
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("Q").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let sampling_dest = ctx
+                .create_sampling_port_destination(Name::from_str("S").unwrap(), 16, Duration::ZERO)
+                .unwrap();
+            let echo = ctx
+                .create_sampling_port_source(Name::from_str("Echo").unwrap(), 16)
+                .unwrap();
+            let mut buf = [0; 16];
+
+            let (validity, sample) = sampling_dest.recv_type_buf::<u32>(&mut buf).unwrap();
+            let mut values = vec![];
+            while let Ok((value, _)) =
+                dest_port.recv_type_buf::<u32>(SystemTime::Infinite, &mut buf)
+            {
+                echo.send_type_buf(value, &mut buf).unwrap();
+                values.push(value);
+            }
+            assert!(sampling_dest.recv_type_buf::<u32>(&mut buf).is_err());
+            (sample, validity, values)
+        });
+        assert_eq!((3, Validity::Invalid, vec![1, 2]), received);
+        assert_eq!(2, sent.len());
+        assert_eq!("Echo", sent[1].port);
+        assert_eq!(10_000_000, sent[1].timestamp);
+    }
+
+    #[test]
+    fn replay_oversized() {
+        let record = |data: &[u8]| Record {
+            port: "Q".into(),
+            timestamp: 0,
+            event: Event::Received { overflow: false },
+            data: data.to_vec(),
+        };
+        let recording = [record(&[1; 17]), record(&[2])];
+
+        let (received, _) = ReplayHyp::replay(recording, |mut ctx| {
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("Q").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 32];
+            let oversized = dest_port.receive(&mut buf, SystemTime::Infinite).err();
+            let (msg, _) = dest_port.receive(&mut buf, SystemTime::Infinite).unwrap();
+            (oversized, msg.to_vec())
+        });
+        assert_eq!((Some(Error::InvalidConfig), vec![2]), received);
+    }
+}
@@ -7,16 +7,21 @@ use alloc::vec;
 extern crate alloc;
 
 use a653rs::prelude::*;
-use postcard::de_flavors::Slice as DeSlice;
-use postcard::ser_flavors::Slice as SerSlice;
 use serde::{Deserialize, Serialize};
 
+use crate::codec::{Decode, Encode, Postcard};
 use crate::error::*;
 
 /// Postcard extension trait for sampling port sources
+///
+/// All sending functions accept either owned values or references,
+/// as `&T` is [`Serialize`] whenever `T` is.
 pub trait SamplingPortSourceExt {
     // Send a type using an a653rs [`SamplingPortSource`]
     ///
+    /// Serializes into a buffer of the port's message size,
+    /// so values too large for the port fail with [`postcard::Error::SerializeBufferFull`].
+    ///
     /// # Example
     /// ```rust
     /// use a653rs_postcard::prelude::*;
@@ -66,6 +71,67 @@ pub trait SamplingPortSourceExt {
     fn send_type_buf<T>(&self, p: T, buf: &mut [u8]) -> Result<(), SendError>
     where
         T: Serialize;
+
+    /// Send a type using an a653rs [`SamplingPortSource`] and the given `codec`
+    ///
+    /// Encodes into a buffer of the port's message size,
+    /// so values too large for the port fail with a codec error like `send_type`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortSource<Hypervisor> = port;
+    /// port.send_type_with(&Postcard, String::from("Typed Data")).unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn send_type_with<C, T>(&self, codec: &C, p: T) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>;
+
+    /// Send a type using an a653rs [`SamplingPortSource`] and the given `codec`
+    ///
+    /// Requires a buffer `buf` for encoding.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortSource<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// port.send_type_buf_with(&Postcard, String::from("Typed Data"), &mut buf)
+    ///     .unwrap();
+    /// # })
+    /// ```
+    fn send_type_buf_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>;
 }
 
 /// Postcard extension trait for sampling port destinations
@@ -83,7 +149,7 @@ pub trait SamplingPortDestinationExt {
     /// # mod mock;
     /// # Hypervisor::run_test(|mut ctx| {
     /// # let src_port = ctx
-    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .create_sampling_port_source(Name::from_str("Port").unwrap(), 500)
     /// #     .unwrap();
     /// # let port = ctx
     /// #     .create_sampling_port_destination(Name::from_str("Port").unwrap(), 500, Duration::ZERO)
@@ -114,7 +180,7 @@ pub trait SamplingPortDestinationExt {
     /// # mod mock;
     /// # Hypervisor::run_test(|mut ctx| {
     /// # let src_port = ctx
-    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .create_sampling_port_source(Name::from_str("Port").unwrap(), 500)
     /// #     .unwrap();
     /// # let port = ctx
     /// #     .create_sampling_port_destination(Name::from_str("Port").unwrap(), 500, Duration::ZERO)
@@ -132,62 +198,264 @@ pub trait SamplingPortDestinationExt {
     ) -> Result<(Validity, T), SamplingRecvBufError<'a>>
     where
         T: for<'b> Deserialize<'b>;
-}
 
-impl<Q: ApexSamplingPortP4Ext> SamplingPortSourceExt for SamplingPortSource<Q> {
-    /// Receive a type using an a653rs [`SamplingPortDestination`]
+    /// Receive a type using an a653rs [`SamplingPortDestination`], overwriting an existing value
     ///
     /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// The received value is deserialized directly into `place`,
+    /// reusing its allocations, see [`Decode::decode_in_place`].
+    /// On failure the contents of `place` are unspecified.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// let mut readings = [0u32; 32];
+    /// # src_port.send_type_buf(&[1u32; 32], &mut buf).unwrap();
+    /// let validity = port.recv_into(&mut readings, &mut buf).unwrap();
+    /// # })
+    /// ```
+    fn recv_into<'a, T>(
+        &self,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>;
+
+    /// Receive a type using an a653rs [`SamplingPortDestination`] and the given `codec`
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// # src_port.send_type(String::default()).unwrap();
+    /// let (validity, received_type): (_, String) = port.recv_type_with(&Postcard).unwrap();
+    /// # })
+    /// ```
+    #[cfg(feature = "alloc")]
+    fn recv_type_with<C, T>(&self, codec: &C) -> Result<(Validity, T), SamplingRecvError<C::Error>>
+    where
+        C: Decode<T>;
+
+    /// Receive a type using an a653rs [`SamplingPortDestination`] and the given `codec`
+    ///
+    /// Requires a buffer `buf` for receiving and decoding the data.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::default(), &mut buf).unwrap();
+    /// let (validity, received_type): (_, String) =
+    ///     port.recv_type_buf_with(&Postcard, &mut buf).unwrap();
+    /// # })
+    /// ```
+    fn recv_type_buf_with<'a, C, T>(
+        &self,
+        codec: &C,
+        buf: &'a mut [u8],
+    ) -> Result<(Validity, T), SamplingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>;
+
+    /// Receive a type into an existing `place` using the given `codec`
+    ///
+    /// Requires a buffer `buf` for receiving and decoding the data.
+    /// On failure the contents of `place` are unspecified, see [`Decode::decode_in_place`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let src_port = ctx
+    /// #     .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+    /// #     .unwrap();
+    /// # let port = ctx
+    /// #     .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+    /// #     .unwrap();
+    ///
+    /// let port: SamplingPortDestination<Hypervisor> = port;
+    /// let mut buf = [0; 500];
+    /// # src_port.send_type_buf(String::from("Typed Data"), &mut buf).unwrap();
+    /// let mut received = String::new();
+    /// let validity = port.recv_into_with(&Postcard, &mut received, &mut buf).unwrap();
+    /// # })
+    /// ```
+    fn recv_into_with<'a, C, T>(
+        &self,
+        codec: &C,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>;
+}
+
+impl<Q: ApexSamplingPortP4Ext> SamplingPortSourceExt for SamplingPortSource<Q> {
     #[cfg(feature = "alloc")]
     fn send_type<T>(&self, p: T) -> Result<(), SendError>
     where
         T: Serialize,
     {
-        let msg = postcard::to_allocvec(&p)?;
-        self.send(&msg).map_err(SendError::from)
+        self.send_type_with(&Postcard, p)
     }
 
     fn send_type_buf<T>(&self, p: T, buf: &mut [u8]) -> Result<(), SendError>
     where
         T: Serialize,
     {
-        let buf =
-            postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(&p, SerSlice::new(buf))?;
+        self.send_type_buf_with(&Postcard, p, buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn send_type_with<C, T>(&self, codec: &C, p: T) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        let mut buf = vec![0; self.size() as usize];
+        self.send_type_buf_with(codec, p, &mut buf)
+    }
+
+    fn send_type_buf_with<C, T>(
+        &self,
+        codec: &C,
+        p: T,
+        buf: &mut [u8],
+    ) -> Result<(), SendError<C::Error>>
+    where
+        C: Encode<T>,
+    {
+        let buf = codec.encode(&p, buf).map_err(SendError::Postcard)?;
         self.send(buf).map_err(SendError::from)
     }
 }
 
 impl<Q: ApexSamplingPortP4Ext> SamplingPortDestinationExt for SamplingPortDestination<Q> {
     #[cfg(feature = "alloc")]
-    fn recv_type<'a, T>(&self) -> Result<(Validity, T), SamplingRecvError>
+    fn recv_type<T>(&self) -> Result<(Validity, T), SamplingRecvError>
     where
         T: for<'b> Deserialize<'b>,
+    {
+        self.recv_type_with(&Postcard)
+    }
+
+    fn recv_type_buf<'a, T>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<(Validity, T), SamplingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.recv_type_buf_with(&Postcard, buf)
+    }
+
+    fn recv_into<'a, T>(
+        &self,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a>>
+    where
+        T: for<'b> Deserialize<'b>,
+    {
+        self.recv_into_with(&Postcard, place, buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn recv_type_with<C, T>(&self, codec: &C) -> Result<(Validity, T), SamplingRecvError<C::Error>>
+    where
+        C: Decode<T>,
     {
         let mut buf = vec![0; self.size() as usize];
         let (val, msg) = self.receive(&mut buf)?;
-        match postcard::from_bytes(msg) {
+        match codec.decode(msg) {
             Ok(t) => Ok((val, t)),
             Err(e) => {
                 let msg_len = msg.len();
                 buf.truncate(msg_len);
-                Err(SamplingRecvError::Postcard(e, val, buf))
+                Err(SamplingRecvError::decode::<C>(e, val, buf))
             }
         }
     }
 
-    fn recv_type_buf<'a, T>(
+    fn recv_type_buf_with<'a, C, T>(
         &self,
+        codec: &C,
         buf: &'a mut [u8],
-    ) -> Result<(Validity, T), SamplingRecvBufError<'a>>
+    ) -> Result<(Validity, T), SamplingRecvBufError<'a, C::Error>>
     where
-        T: for<'b> Deserialize<'b>,
+        C: Decode<T>,
     {
         let (val, msg) = self.receive(buf)?;
-        let msg_slice = DeSlice::new(msg);
-        let mut deserializer = postcard::Deserializer::from_flavor(msg_slice);
-        match T::deserialize(&mut deserializer) {
+        match codec.decode(msg) {
             Ok(t) => Ok((val, t)),
-            Err(e) => Err(SamplingRecvBufError::Postcard(e, val, msg)),
+            Err(e) => Err(SamplingRecvBufError::decode::<C>(e, val, msg)),
+        }
+    }
+
+    fn recv_into_with<'a, C, T>(
+        &self,
+        codec: &C,
+        place: &mut T,
+        buf: &'a mut [u8],
+    ) -> Result<Validity, SamplingRecvBufError<'a, C::Error>>
+    where
+        C: Decode<T>,
+    {
+        let (val, msg) = self.receive(buf)?;
+        match codec.decode_in_place(msg, place) {
+            Ok(()) => Ok(val),
+            Err(e) => Err(SamplingRecvBufError::decode::<C>(e, val, msg)),
         }
     }
 }
@@ -229,6 +497,33 @@ mod tests {
         })
     }
 
+    #[test]
+    fn sampling_type_by_ref() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+                .unwrap();
+            let dest_port = ctx
+                .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+                .unwrap();
+
+            let msg = String::from("Test");
+            let mut buf = [0; 500];
+
+            src_port.send_type_buf(&msg, &mut buf).unwrap();
+            let (_, rec): (_, String) = dest_port.recv_type_buf(&mut buf).unwrap();
+            assert_eq!(msg, rec);
+
+            #[cfg(feature = "alloc")]
+            {
+                let msg = String::from("Allocated");
+                src_port.send_type(&msg).unwrap();
+                let (_, rec): (_, String) = dest_port.recv_type().unwrap();
+                assert_eq!(msg, rec);
+            }
+        })
+    }
+
     #[test]
     fn const_sampling_type_buf() {
         MockHyp::run_test(|mut ctx| {
@@ -252,6 +547,37 @@ mod tests {
         })
     }
 
+    #[test]
+    fn sampling_recv_into() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 500)
+                .unwrap();
+            let dest_port = ctx
+                .create_sampling_port_destination(Name::from_str("").unwrap(), 500, Duration::ZERO)
+                .unwrap();
+
+            let msg = [1u32, 2, 3, 4];
+            let mut rec = [0u32; 4];
+            let mut buf = [0; 500];
+
+            src_port.send_type_buf(msg, &mut buf).unwrap();
+            dest_port.recv_into(&mut rec, &mut buf).unwrap();
+            assert_eq!(msg, rec);
+
+            src_port.send_type_buf(true, &mut buf).unwrap();
+            let mut rec = String::from("Old");
+            dest_port.recv_into(&mut rec, &mut buf).unwrap_err();
+            assert_eq!("Old", rec);
+
+            // Fails after deserializing the first two elements in place
+            src_port.send_type_buf([1u32, 2], &mut buf).unwrap();
+            let mut rec = [9u32; 4];
+            dest_port.recv_into(&mut rec, &mut buf).unwrap_err();
+            assert_eq!([1, 2, 9, 9], rec)
+        })
+    }
+
     #[cfg(feature = "alloc")]
     #[test]
     fn sampling_type() {
@@ -268,7 +594,22 @@ mod tests {
             src_port.send_type(msg.clone()).unwrap();
             let (_, rec): (_, String) = dest_port.recv_type().unwrap();
 
-            assert_eq!(msg, rec)
+            assert_eq!(msg, rec);
+
+            // Values too large for the port fail alike with and without a codec
+            let large = "x".repeat(501);
+            assert!(matches!(
+                src_port.send_type(&large),
+                Err(crate::error::SendError::Postcard(
+                    postcard::Error::SerializeBufferFull
+                ))
+            ));
+            assert!(matches!(
+                src_port.send_type_with(&crate::codec::Postcard, &large),
+                Err(crate::error::SendError::Postcard(
+                    postcard::Error::SerializeBufferFull
+                ))
+            ));
         })
     }
 
@@ -0,0 +1,469 @@
+//! Waiting on multiple typed queuing port receivers
+
+use core::marker::PhantomData;
+use core::ops::Range;
+use core::time::Duration;
+
+use a653rs::prelude::*;
+use serde::Deserialize;
+
+use crate::error::*;
+use crate::queuing::QueuingPortReceiverExt;
+
+/// Receiver which can be waited on by a [`Select`]
+pub trait SelectArm<E> {
+    /// Receive and map a message without waiting
+    ///
+    /// Returns `None` if no message is available.
+    /// The data of a [`QueuingRecvBufError::Postcard`] must be a part of `buf`,
+    /// otherwise [`Select`] reports the error without data.
+    fn poll<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(E, QueueOverflow)>, QueuingRecvBufError<'a>>;
+}
+
+/// [`SelectArm`] receiving a type `T` and mapping it into the common type `E`
+///
+/// The mapping is usually an enum variant constructor, tagging which port received a value.
+pub struct Arm<'p, Q: ApexQueuingPortP4Ext, T, F> {
+    port: &'p QueuingPortReceiver<Q>,
+    map: F,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<'p, Q: ApexQueuingPortP4Ext, T, F> Arm<'p, Q, T, F> {
+    /// Receive `T` from `port`, mapping it with `map`
+    pub fn new(port: &'p QueuingPortReceiver<Q>, map: F) -> Self {
+        Self {
+            port,
+            map,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<Q, T, E, F> SelectArm<E> for Arm<'_, Q, T, F>
+where
+    Q: ApexQueuingPortP4Ext,
+    T: for<'b> Deserialize<'b>,
+    F: Fn(T) -> E,
+{
+    fn poll<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<Option<(E, QueueOverflow)>, QueuingRecvBufError<'a>> {
+        let received = self.port.try_recv_type_buf::<T>(buf)?;
+        Ok(received.map(|(t, overflow)| ((self.map)(t), overflow)))
+    }
+}
+
+/// Failure of a polling round, not borrowing the buffer
+///
+/// Only the range of the failed message within the buffer is kept.
+struct PollError {
+    index: usize,
+    error: QueuingRecvBufError<'static>,
+    range: Option<Range<usize>>,
+}
+
+/// Order in which the arms of a [`Select`] are polled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fairness {
+    /// Always poll the first arm first
+    ///
+    /// After an arm failed, the next round starts with the arm after it,
+    /// so that an arm failing repeatedly does not starve the arms after it.
+    Priority,
+    /// Start polling with the arm after the one which last received a message
+    #[default]
+    RoundRobin,
+}
+
+/// Message received by a [`Select`]
+#[derive(Debug, Clone)]
+pub struct Selected<E> {
+    /// Index of the arm which received the message
+    pub index: usize,
+    /// Mapped message
+    pub value: E,
+    /// Whether the queue of the arm overflowed
+    pub overflow: QueueOverflow,
+}
+
+/// Waits on `N` queuing port receivers of possibly different types
+///
+/// There is no hypervisor service for waiting on multiple ports,
+/// so all arms are polled and the calling process waits for `poll_interval`
+/// between polling rounds.
+pub struct Select<'a, H, E, const N: usize> {
+    arms: [&'a dyn SelectArm<E>; N],
+    poll_interval: Duration,
+    fairness: Fairness,
+    next: usize,
+    failed: bool,
+    _h: PhantomData<H>,
+}
+
+impl<'a, H: ApexTimeP1Ext, E, const N: usize> Select<'a, H, E, N> {
+    /// Wait on `arms`, polling them every `poll_interval`
+    pub fn new(arms: [&'a dyn SelectArm<E>; N], poll_interval: Duration) -> Self {
+        Self {
+            arms,
+            poll_interval,
+            fairness: Fairness::default(),
+            next: 0,
+            failed: false,
+            _h: PhantomData,
+        }
+    }
+
+    /// Use the given [`Fairness`] policy
+    pub fn with_fairness(mut self, fairness: Fairness) -> Self {
+        self.fairness = fairness;
+        self
+    }
+
+    /// Wait until any arm receives a message
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Fails with [`Error::NotAvailable`] if `timeout` is zero and no message is available,
+    /// or with [`Error::TimedOut`] if no message arrived before `timeout` passed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let cmd_src = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("CMD").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let cmd_port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("CMD").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let tm_port = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("TM").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let mut buf = [0; 500];
+    /// # cmd_src.send_type_buf("Reset", SystemTime::Infinite, &mut buf).unwrap();
+    ///
+    /// enum Event {
+    ///     Command(String),
+    ///     Telemetry(u32),
+    /// }
+    ///
+    /// let cmd_port: QueuingPortReceiver<Hypervisor> = cmd_port;
+    /// let tm_port: QueuingPortReceiver<Hypervisor> = tm_port;
+    /// let cmd = Arm::new(&cmd_port, Event::Command);
+    /// let tm = Arm::new(&tm_port, Event::Telemetry);
+    /// let mut select = Select::<Hypervisor, _, 2>::new([&cmd, &tm], Duration::from_millis(1));
+    ///
+    /// match select.select_buf(SystemTime::Normal(Duration::from_millis(10)), &mut buf) {
+    ///     Ok(Selected { value: Event::Command(cmd), .. }) => { /* Handle command */ }
+    ///     Ok(Selected { value: Event::Telemetry(tm), .. }) => { /* Handle telemetry */ }
+    ///     Err(e) => { /* Report error */ }
+    /// }
+    /// # })
+    /// ```
+    pub fn select_buf<'b>(
+        &mut self,
+        timeout: SystemTime,
+        buf: &'b mut [u8],
+    ) -> Result<Selected<E>, SelectError<'b>> {
+        let deadline = match timeout {
+            SystemTime::Infinite => None,
+            SystemTime::Normal(timeout) => Some(Self::now().saturating_add(timeout)),
+        };
+        loop {
+            match self.poll_round(buf) {
+                Ok(Some(selected)) => return Ok(selected),
+                Ok(None) => {}
+                Err(PollError {
+                    index,
+                    error,
+                    range,
+                }) => {
+                    let msg = range.and_then(|r| buf.get(r)).unwrap_or_default();
+                    return Err(SelectError::Port(index, error.with_msg(msg)));
+                }
+            }
+
+            let wait = match deadline {
+                None => self.poll_interval,
+                Some(deadline) => {
+                    let remaining = deadline.saturating_sub(Self::now());
+                    if remaining.is_zero() {
+                        return Err(SelectError::Apex(match timeout {
+                            SystemTime::Normal(Duration::ZERO) => Error::NotAvailable,
+                            _ => Error::TimedOut,
+                        }));
+                    }
+                    remaining.min(self.poll_interval)
+                }
+            };
+            <H as ApexTimeP1Ext>::timed_wait(wait).map_err(SelectError::Apex)?;
+        }
+    }
+
+    /// Poll all arms once, in the order given by the [`Fairness`] policy
+    fn poll_round(&mut self, buf: &mut [u8]) -> Result<Option<Selected<E>>, PollError> {
+        let start = match self.fairness {
+            Fairness::Priority if !self.failed => 0,
+            _ => self.next,
+        };
+        self.failed = false;
+        let bounds = BufBounds::of(buf);
+        for offset in 0..N {
+            let index = (start + offset) % N;
+            match self.arms[index].poll(buf) {
+                Ok(Some((value, overflow))) => {
+                    self.next = (index + 1) % N;
+                    return Ok(Some(Selected {
+                        index,
+                        value,
+                        overflow,
+                    }));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.next = (index + 1) % N;
+                    self.failed = true;
+                    let range = e.msg().and_then(|msg| bounds.range(msg));
+                    return Err(PollError {
+                        index,
+                        error: e.with_msg(&[]),
+                        range,
+                    });
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn now() -> Duration {
+        match <H as ApexTimeP4Ext>::get_time() {
+            SystemTime::Normal(now) => now,
+            SystemTime::Infinite => Duration::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+    use std::string::String;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{ApexTimeP4Ext, Error, Name, QueueOverflow, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Command(String),
+        Telemetry(u32),
+    }
+
+    #[test]
+    fn select_round_robin() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["CMD", "TM"].map(|n| Name::from_str(n).unwrap());
+            let [cmd_src, tm_src] = names.clone().map(|name| {
+                ctx.create_queuing_port_sender(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let [cmd_port, tm_port] = names.map(|name| {
+                ctx.create_queuing_port_receiver(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let cmd = Arm::new(&cmd_port, Event::Command);
+            let tm = Arm::new(&tm_port, Event::Telemetry);
+            let mut select = Select::<MockHyp, _, 2>::new([&cmd, &tm], Duration::from_millis(1));
+            let mut buf = [0; 100];
+
+            cmd_src
+                .send_types_buf(["A", "B"], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            tm_src
+                .send_type_buf(7u32, SystemTime::Infinite, &mut buf)
+                .unwrap();
+
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            let selected = select.select_buf(timeout.clone(), &mut buf).unwrap();
+            assert_eq!(Event::Command(String::from("A")), selected.value);
+            let selected = select.select_buf(timeout.clone(), &mut buf).unwrap();
+            assert_eq!(Event::Telemetry(7), selected.value);
+            assert_eq!(1, selected.index);
+            let selected = select.select_buf(timeout.clone(), &mut buf).unwrap();
+            assert_eq!(Event::Command(String::from("B")), selected.value);
+            assert!(matches!(
+                select.select_buf(timeout, &mut buf),
+                Err(SelectError::Apex(Error::NotAvailable))
+            ));
+        })
+    }
+
+    #[test]
+    fn select_deadline() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let tm = Arm::new(&dest_port, Event::Telemetry);
+            let mut select = Select::<MockHyp, _, 1>::new([&tm], Duration::from_millis(3))
+                .with_fairness(Fairness::Priority);
+            let mut buf = [0; 100];
+
+            let timeout = SystemTime::Normal(Duration::from_millis(10));
+            assert!(matches!(
+                select.select_buf(timeout.clone(), &mut buf),
+                Err(SelectError::Apex(Error::TimedOut))
+            ));
+            assert_eq!(
+                SystemTime::Normal(Duration::from_millis(10)),
+                <MockHyp as ApexTimeP4Ext>::get_time()
+            );
+
+            src_port
+                .send_type_buf(u64::MAX, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert!(matches!(
+                select.select_buf(timeout, &mut buf),
+                Err(SelectError::Port(0, QueuingRecvBufError::Postcard(_, msg))) if msg.len() == 10
+            ));
+        })
+    }
+
+    /// Arm whose port always fails, reporting the middle of the buffer as data
+    struct Failing(bool);
+
+    impl SelectArm<Event> for Failing {
+        fn poll<'a>(
+            &self,
+            buf: &'a mut [u8],
+        ) -> Result<Option<(Event, QueueOverflow)>, QueuingRecvBufError<'a>> {
+            match self.0 {
+                true => Err(QueuingRecvBufError::Apex(Error::InvalidConfig)),
+                false => {
+                    buf[2..4].copy_from_slice(&[0xAA, 0xBB]);
+                    let e = postcard::Error::DeserializeBadEncoding;
+                    Err(QueuingRecvBufError::Postcard(e, &buf[2..4]))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn select_failing_arm() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    100,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let failing = Failing(true);
+            let corrupt = Failing(false);
+            let tm = Arm::new(&dest_port, Event::Telemetry);
+            let mut select =
+                Select::<MockHyp, _, 3>::new([&failing, &corrupt, &tm], Duration::from_millis(1));
+            let mut buf = [0; 100];
+
+            src_port
+                .send_type_buf(7u32, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            assert!(matches!(
+                select.select_buf(timeout.clone(), &mut buf),
+                Err(SelectError::Port(0, QueuingRecvBufError::Apex(_)))
+            ));
+            assert!(matches!(
+                select.select_buf(timeout.clone(), &mut buf),
+                Err(SelectError::Port(
+                    1,
+                    QueuingRecvBufError::Postcard(_, [0xAA, 0xBB])
+                ))
+            ));
+            let selected = select.select_buf(timeout, &mut buf).unwrap();
+            assert_eq!(Event::Telemetry(7), selected.value);
+        })
+    }
+
+    #[test]
+    fn select_priority_failing_arm() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["CMD", "TM"].map(|n| Name::from_str(n).unwrap());
+            let [cmd_src, tm_src] = names.clone().map(|name| {
+                ctx.create_queuing_port_sender(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let [cmd_port, tm_port] = names.map(|name| {
+                ctx.create_queuing_port_receiver(name, 100, 10, QueuingDiscipline::Fifo)
+                    .unwrap()
+            });
+            let failing = Failing(true);
+            let cmd = Arm::new(&cmd_port, Event::Command);
+            let tm = Arm::new(&tm_port, Event::Telemetry);
+            let mut select =
+                Select::<MockHyp, _, 3>::new([&cmd, &failing, &tm], Duration::from_millis(1))
+                    .with_fairness(Fairness::Priority);
+            let mut buf = [0; 100];
+
+            tm_src
+                .send_types_buf([1u32, 2], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let timeout = SystemTime::Normal(Duration::ZERO);
+            for expected in [1, 2] {
+                assert!(matches!(
+                    select.select_buf(timeout.clone(), &mut buf),
+                    Err(SelectError::Port(1, QueuingRecvBufError::Apex(_)))
+                ));
+                let selected = select.select_buf(timeout.clone(), &mut buf).unwrap();
+                assert_eq!(Event::Telemetry(expected), selected.value);
+            }
+
+            // Priority order is restored after rotating past the failing arm
+            cmd_src
+                .send_type_buf("Reset", SystemTime::Infinite, &mut buf)
+                .unwrap();
+            let selected = select.select_buf(timeout, &mut buf).unwrap();
+            assert_eq!(Event::Command(String::from("Reset")), selected.value);
+        })
+    }
+}
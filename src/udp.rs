@@ -0,0 +1,450 @@
+//! Mirroring ports to UDP sockets for software-in-the-loop testing
+//!
+//! Every datagram carries exactly one port message,
+//! so datagrams use the same postcard framing as the ports.
+
+extern crate std;
+
+use core::time::Duration;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::vec::Vec;
+
+use a653rs::prelude::*;
+use postcard::de_flavors::Slice as DeSlice;
+use postcard::ser_flavors::Slice as SerSlice;
+use serde::{Deserialize, Serialize};
+
+use crate::error::*;
+use crate::fan_out::MessageSender;
+use crate::forward::{MessageReceiver, ReceiveInfo};
+
+/// Result of polling a [`UdpBridge`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Polled {
+    /// Number of forwarded messages and datagrams
+    pub forwarded: usize,
+    /// Number of dropped messages and datagrams
+    ///
+    /// Messages are dropped if sending them to their peer failed,
+    /// and datagrams if they were empty, too long or rejected by their port.
+    pub dropped: usize,
+}
+
+/// Default maximum of messages or datagrams forwarded per port or socket and poll
+const DEFAULT_MAX_PER_POLL: usize = 64;
+
+/// Bridge between ports and UDP sockets
+///
+/// Every port is mirrored using its own socket.
+/// Messages received on outbound ports are sent as datagrams to a peer,
+/// and datagrams received on inbound sockets are sent to a port.
+/// The bridge never blocks, so [`UdpBridge::poll_buf`] is meant to be called periodically.
+pub struct UdpBridge<'a> {
+    outbound: Vec<(&'a dyn MessageReceiver, UdpSocket, SocketAddr)>,
+    inbound: Vec<(UdpSocket, &'a dyn MessageSender)>,
+    max_per_poll: usize,
+}
+
+impl Default for UdpBridge<'_> {
+    fn default() -> Self {
+        Self {
+            outbound: Vec::new(),
+            inbound: Vec::new(),
+            max_per_poll: DEFAULT_MAX_PER_POLL,
+        }
+    }
+}
+
+impl<'a> UdpBridge<'a> {
+    /// Bridge without any ports
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward at most `max` messages or datagrams per port or socket on every poll
+    ///
+    /// Bounds the time spent in [`UdpBridge::poll_buf`] if a peer keeps sending.
+    /// Remaining messages and datagrams are forwarded by the next polls.
+    /// Defaults to 64.
+    pub fn with_max_per_poll(mut self, max: usize) -> Self {
+        self.max_per_poll = max;
+        self
+    }
+
+    /// Send messages received on `port` to `peer` using `socket`
+    ///
+    /// Queuing ports are drained on every poll,
+    /// while the current message of sampling ports is sent once per poll.
+    pub fn with_outbound(
+        mut self,
+        port: &'a dyn MessageReceiver,
+        socket: UdpSocket,
+        peer: SocketAddr,
+    ) -> Self {
+        self.outbound.push((port, socket, peer));
+        self
+    }
+
+    /// Send datagrams received on `socket` to `port`
+    ///
+    /// Fails if `socket` can not be switched to non-blocking mode.
+    pub fn with_inbound(
+        mut self,
+        socket: UdpSocket,
+        port: &'a dyn MessageSender,
+    ) -> std::io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        self.inbound.push((socket, port));
+        Ok(self)
+    }
+
+    /// Forward all pending messages and datagrams
+    ///
+    /// Requires a buffer `buf` for receiving messages and datagrams.
+    /// It must be larger than the largest port,
+    /// as datagrams filling the whole buffer may be truncated and are dropped.
+    /// Datagrams are sent to their port without waiting,
+    /// so datagrams rejected by their port, e.g. because its queue is full, are dropped as well.
+    /// Messages which could not be sent to their peer are dropped.
+    /// Every port and socket forwards at most the maximum set by
+    /// [`UdpBridge::with_max_per_poll`].
+    /// Returns the number of forwarded and dropped messages and datagrams.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let telemetry = ctx
+    /// #     .create_queuing_port_receiver(Name::from_str("TM").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// # let commands = ctx
+    /// #     .create_queuing_port_sender(Name::from_str("CMD").unwrap(), 500, 10, QueuingDiscipline::Fifo)
+    /// #     .unwrap();
+    /// use std::net::UdpSocket;
+    ///
+    /// let telemetry: QueuingPortReceiver<Hypervisor> = telemetry;
+    /// let commands: QueuingPortSender<Hypervisor> = commands;
+    /// let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let bridge = UdpBridge::new()
+    ///     .with_outbound(
+    ///         &telemetry,
+    ///         UdpSocket::bind("127.0.0.1:0").unwrap(),
+    ///         ground.local_addr().unwrap(),
+    ///     )
+    ///     .with_inbound(UdpSocket::bind("127.0.0.1:0").unwrap(), &commands)
+    ///     .unwrap();
+    /// let mut buf = [0; 501];
+    /// let polled = bridge.poll_buf(&mut buf).unwrap();
+    /// assert_eq!(0, polled.dropped);
+    /// # })
+    /// ```
+    pub fn poll_buf(&self, buf: &mut [u8]) -> Result<Polled, BridgeError> {
+        let mut polled = Polled::default();
+        for (port, socket, peer) in &self.outbound {
+            for _ in 0..self.max_per_poll {
+                match port.recv_msg(buf, SystemTime::Normal(Duration::ZERO)) {
+                    Ok((msg, info)) => {
+                        match socket.send_to(msg, peer) {
+                            Ok(_) => polled.forwarded += 1,
+                            Err(_) => polled.dropped += 1,
+                        }
+                        if let ReceiveInfo::Sampling(_) = info {
+                            break;
+                        }
+                    }
+                    Err(Error::NotAvailable | Error::NoAction) => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        for (socket, port) in &self.inbound {
+            for _ in 0..self.max_per_poll {
+                match socket.recv(buf) {
+                    Ok(len) if len < buf.len() => {
+                        match port.send_msg(&buf[..len], SystemTime::Normal(Duration::ZERO)) {
+                            Ok(()) => polled.forwarded += 1,
+                            Err(_) => polled.dropped += 1,
+                        }
+                    }
+                    Ok(_) => polled.dropped += 1,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(polled)
+    }
+}
+
+/// Postcard extension trait for UDP sockets, e.g. for ground-side tools talking to a [`UdpBridge`]
+pub trait UdpSocketExt {
+    /// Send a type as a single datagram to `peer`
+    ///
+    /// Requires a buffer `buf` for serialization.
+    fn send_type_to_buf<T>(&self, p: T, peer: SocketAddr, buf: &mut [u8]) -> Result<(), UdpError>
+    where
+        T: Serialize;
+
+    /// Receive a type from a single datagram
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// Also returns the address of the sender.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// use std::net::UdpSocket;
+    ///
+    /// let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let mut buf = [0; 500];
+    /// a.send_type_to_buf("Command", b.local_addr().unwrap(), &mut buf)
+    ///     .unwrap();
+    /// let (cmd, _) = b.recv_type_from_buf::<&str>(&mut buf).unwrap();
+    /// assert_eq!("Command", cmd);
+    /// ```
+    fn recv_type_from_buf<'a, T>(&self, buf: &'a mut [u8]) -> Result<(T, SocketAddr), UdpError>
+    where
+        T: Deserialize<'a>;
+}
+
+impl UdpSocketExt for UdpSocket {
+    fn send_type_to_buf<T>(&self, p: T, peer: SocketAddr, buf: &mut [u8]) -> Result<(), UdpError>
+    where
+        T: Serialize,
+    {
+        let buf =
+            postcard::serialize_with_flavor::<T, SerSlice, &mut [u8]>(&p, SerSlice::new(buf))?;
+        self.send_to(buf, peer)?;
+        Ok(())
+    }
+
+    fn recv_type_from_buf<'a, T>(&self, buf: &'a mut [u8]) -> Result<(T, SocketAddr), UdpError>
+    where
+        T: Deserialize<'a>,
+    {
+        let (len, peer) = self.recv_from(buf)?;
+        let mut de = postcard::Deserializer::from_flavor(DeSlice::new(&buf[..len]));
+        let t = T::deserialize(&mut de)?;
+        Ok((t, peer))
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+    use std::net::UdpSocket;
+    use std::string::String;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[test]
+    fn udp_bridge_roundtrip() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["TM", "CMD", "STATE"].map(|n| Name::from_str(n).unwrap());
+            let tm_src = ctx
+                .create_queuing_port_sender(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let tm_dest = ctx
+                .create_queuing_port_receiver(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let cmd_src = ctx
+                .create_queuing_port_sender(names[1].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let cmd_dest = ctx
+                .create_queuing_port_receiver(names[1].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let state_src = ctx
+                .create_sampling_port_source(names[2].clone(), 100)
+                .unwrap();
+            let state_dest = ctx
+                .create_sampling_port_destination(names[2].clone(), 100, Duration::ZERO)
+                .unwrap();
+            let mut buf = [0; 101];
+
+            let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+            ground
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .unwrap();
+            let inbound = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let inbound_addr = inbound.local_addr().unwrap();
+            let bridge = UdpBridge::new()
+                .with_outbound(
+                    &tm_dest,
+                    UdpSocket::bind("127.0.0.1:0").unwrap(),
+                    ground.local_addr().unwrap(),
+                )
+                .with_outbound(
+                    &state_dest,
+                    UdpSocket::bind("127.0.0.1:0").unwrap(),
+                    ground.local_addr().unwrap(),
+                )
+                .with_inbound(inbound, &cmd_src)
+                .unwrap();
+
+            // Nothing to forward yet
+            assert_eq!(Polled::default(), bridge.poll_buf(&mut buf).unwrap());
+
+            tm_src
+                .send_types_buf([1u32, 2], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            state_src.send_type_buf(true, &mut buf).unwrap();
+            assert_eq!(3, bridge.poll_buf(&mut buf).unwrap().forwarded);
+            let (tm, _) = ground.recv_type_from_buf::<u32>(&mut buf).unwrap();
+            assert_eq!(1, tm);
+            let (tm, _) = ground.recv_type_from_buf::<u32>(&mut buf).unwrap();
+            assert_eq!(2, tm);
+            let (state, _) = ground.recv_type_from_buf::<bool>(&mut buf).unwrap();
+            assert!(state);
+
+            ground
+                .send_type_to_buf("Reset", inbound_addr, &mut buf)
+                .unwrap();
+            // The datagram may take a moment to arrive on the loopback interface
+            let mut forwarded = 0;
+            for _ in 0..100 {
+                forwarded = bridge.poll_buf(&mut buf).unwrap().forwarded;
+                if forwarded > 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            // Sampling ports are sent on every poll
+            assert_eq!(2, forwarded);
+            let (cmd, _) = cmd_dest
+                .recv_type_buf::<String>(SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!("Reset", cmd);
+        })
+    }
+
+    #[test]
+    fn udp_bridge_drops_invalid() {
+        MockHyp::run_test(|mut ctx| {
+            let cmd_src = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("CMD").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let cmd_dest = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("CMD").unwrap(),
+                    4,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let mut buf = [0; 8];
+
+            let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let inbound = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let inbound_addr = inbound.local_addr().unwrap();
+            let bridge = UdpBridge::new().with_inbound(inbound, &cmd_src).unwrap();
+
+            // Empty, too long for the port, too long for the buffer and valid
+            for datagram in [&[][..], &[1; 5], &[2; 20], &[3; 4]] {
+                ground.send_to(datagram, inbound_addr).unwrap();
+            }
+            // The datagrams may take a moment to arrive on the loopback interface
+            let mut polled = Polled::default();
+            for _ in 0..100 {
+                let p = bridge.poll_buf(&mut buf).unwrap();
+                polled.forwarded += p.forwarded;
+                polled.dropped += p.dropped;
+                if polled.forwarded + polled.dropped == 4 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(
+                Polled {
+                    forwarded: 1,
+                    dropped: 3
+                },
+                polled
+            );
+            let (msg, _) = cmd_dest
+                .receive(&mut buf, SystemTime::Normal(Duration::ZERO))
+                .unwrap();
+            assert_eq!(&[3; 4], msg);
+        })
+    }
+
+    #[test]
+    fn udp_bridge_limits_poll() {
+        MockHyp::run_test(|mut ctx| {
+            let names = ["TM", "LOST"].map(|n| Name::from_str(n).unwrap());
+            let tm_src = ctx
+                .create_queuing_port_sender(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let tm_dest = ctx
+                .create_queuing_port_receiver(names[0].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let lost_src = ctx
+                .create_queuing_port_sender(names[1].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let lost_dest = ctx
+                .create_queuing_port_receiver(names[1].clone(), 100, 10, QueuingDiscipline::Fifo)
+                .unwrap();
+            let mut buf = [0; 101];
+
+            let ground = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let bridge = UdpBridge::new()
+                // Sending to port zero fails
+                .with_outbound(
+                    &lost_dest,
+                    UdpSocket::bind("127.0.0.1:0").unwrap(),
+                    "127.0.0.1:0".parse().unwrap(),
+                )
+                .with_outbound(
+                    &tm_dest,
+                    UdpSocket::bind("127.0.0.1:0").unwrap(),
+                    ground.local_addr().unwrap(),
+                )
+                .with_max_per_poll(2);
+
+            lost_src
+                .send_types_buf([1u32, 2, 3], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            tm_src
+                .send_types_buf([1u32, 2, 3], SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(
+                Polled {
+                    forwarded: 2,
+                    dropped: 2
+                },
+                bridge.poll_buf(&mut buf).unwrap()
+            );
+            assert_eq!(
+                Polled {
+                    forwarded: 1,
+                    dropped: 1
+                },
+                bridge.poll_buf(&mut buf).unwrap()
+            );
+            assert_eq!(Polled::default(), bridge.poll_buf(&mut buf).unwrap());
+        })
+    }
+}
@@ -0,0 +1,212 @@
+//! Semantic validation of sent and received values
+//!
+//! Types implementing [`Validate`] check their range constraints and invariants.
+//! The [`Validated`] codec wrapper enforces these checks on every value
+//! passing a port, after decoding and before encoding.
+
+use crate::codec::{Codec, Decode, Encode, Postcard};
+use crate::error::{DecodeFailure, ValidatedError, ValidationError};
+
+/// Value with semantic constraints
+pub trait Validate {
+    /// Check constraints which are not expressed by the type itself
+    fn validate(&self) -> Result<(), ValidationError>;
+}
+
+/// Codec validating values encoded and decoded by the inner codec `C`
+///
+/// Values failing validation are never sent.
+/// Received values failing validation are discarded and reported in the `Validation` variants
+/// of the receive errors together with the raw message, so `place` of `recv_into_with` is
+/// left untouched.
+/// Typed ports, e.g. [`TypedQueuingPortReceiver`](crate::ports::TypedQueuingPortReceiver),
+/// using this codec validate every value without repeating it at every call.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// # use std::time::Duration;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// # Hypervisor::run_test(|mut ctx| {
+/// # let src_port = ctx
+/// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// # let port = ctx
+/// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// # let mut buf = [0; 16];
+/// # src_port.send_type_buf(120u8, SystemTime::Infinite, &mut buf).unwrap();
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Throttle(u8);
+///
+/// impl Validate for Throttle {
+///     fn validate(&self) -> Result<(), ValidationError> {
+///         if self.0 > 100 {
+///             return Err(ValidationError("throttle above 100 %"));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let port: QueuingPortReceiver<Hypervisor> = port;
+/// let received =
+///     port.recv_type_buf_with::<_, Throttle>(&Validated(Postcard), SystemTime::Infinite, &mut buf);
+/// assert!(matches!(
+///     received,
+///     Err(QueuingRecvBufError::Validation(_, [120]))
+/// ));
+/// # })
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Validated<C = Postcard>(pub C);
+
+/// Failed validation is reported in the `Validation` variants of the receive errors
+impl<C: Codec> Codec for Validated<C> {
+    type Error = ValidatedError<C::Error>;
+
+    fn failure(e: Self::Error) -> DecodeFailure<Self::Error> {
+        match e {
+            ValidatedError::Codec(e) => match C::failure(e) {
+                DecodeFailure::Codec(e) => DecodeFailure::Codec(ValidatedError::Codec(e)),
+                DecodeFailure::Limit(e) => DecodeFailure::Limit(e),
+                DecodeFailure::Validation(e) => DecodeFailure::Validation(e),
+            },
+            ValidatedError::Validation(e) => DecodeFailure::Validation(e),
+        }
+    }
+}
+
+impl<C: Encode<T>, T: Validate + ?Sized> Encode<T> for Validated<C> {
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], Self::Error> {
+        value.validate()?;
+        self.0.encode(value, buf).map_err(ValidatedError::Codec)
+    }
+}
+
+impl<C: Decode<T>, T: Validate> Decode<T> for Validated<C> {
+    fn decode(&self, msg: &[u8]) -> Result<T, Self::Error> {
+        let value = self.0.decode(msg).map_err(ValidatedError::Codec)?;
+        value.validate()?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Range {
+        min: i32,
+        max: i32,
+    }
+
+    impl Validate for Range {
+        fn validate(&self) -> Result<(), ValidationError> {
+            if self.min > self.max {
+                return Err(ValidationError("min exceeds max"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn validate_queuing() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    16,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let codec = Validated(Postcard);
+            let mut buf = [0; 16];
+
+            let valid = Range { min: -1, max: 1 };
+            let invalid = Range { min: 1, max: -1 };
+            src_port
+                .send_type_buf_with(&codec, valid, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert!(matches!(
+                src_port.send_type_buf_with(&codec, invalid, SystemTime::Infinite, &mut buf),
+                Err(SendError::Postcard(ValidatedError::Validation(_)))
+            ));
+            src_port
+                .send_type_buf(invalid, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            src_port
+                .send_type_buf(u64::MAX, SystemTime::Infinite, &mut buf)
+                .unwrap();
+
+            let (rec, _) = dest_port
+                .recv_type_buf_with::<_, Range>(&codec, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            assert_eq!(valid, rec);
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, Range>(&codec, SystemTime::Infinite, &mut buf),
+                Err(QueuingRecvBufError::Validation(
+                    ValidationError("min exceeds max"),
+                    [2, 1]
+                ))
+            ));
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, Range>(&codec, SystemTime::Infinite, &mut buf),
+                Err(QueuingRecvBufError::Postcard(ValidatedError::Codec(_), _))
+            ));
+        })
+    }
+
+    #[test]
+    fn validate_sampling_in_place() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_sampling_port_source(Name::from_str("").unwrap(), 16)
+                .unwrap();
+            let dest_port = ctx
+                .create_sampling_port_destination(Name::from_str("").unwrap(), 16, Duration::ZERO)
+                .unwrap();
+            let codec = Validated(Postcard);
+            let mut buf = [0; 16];
+            let mut rec = Range { min: 0, max: 0 };
+
+            src_port
+                .send_type_buf(Range { min: 5, max: 3 }, &mut buf)
+                .unwrap();
+            assert!(matches!(
+                dest_port.recv_into_with(&codec, &mut rec, &mut buf),
+                Err(SamplingRecvBufError::Validation(_, _, [10, 6]))
+            ));
+            assert_eq!(Range { min: 0, max: 0 }, rec);
+        })
+    }
+}
@@ -0,0 +1,319 @@
+//! Versioned messages with upgrades from older versions
+//!
+//! Every message starts with its version number as postcard varint,
+//! followed by the postcard-encoded value.
+//! Receivers decode messages of the current version directly,
+//! and messages of older versions using registered [`Upgrade`]s.
+//! This allows sender and receiver partitions to be updated independently.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::{Codec, Decode, Encode, Postcard};
+use crate::error::VersionError;
+
+/// Decoder of a message of an older version into the current type `T`
+pub struct Upgrade<T> {
+    /// Version of decoded messages
+    pub version: u32,
+    /// Decode the value following the version number
+    pub decode: fn(&[u8]) -> Result<T, postcard::Error>,
+}
+
+impl<T> Upgrade<T> {
+    /// Upgrade messages of `version` containing an `O`, converting it into `T`
+    pub const fn from<O>(version: u32) -> Self
+    where
+        O: for<'a> Deserialize<'a> + Into<T>,
+    {
+        Self {
+            version,
+            decode: decode_into::<O, T>,
+        }
+    }
+}
+
+impl<T> Clone for Upgrade<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Upgrade<T> {}
+
+impl<T> fmt::Debug for Upgrade<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgrade")
+            .field("version", &self.version)
+            .finish_non_exhaustive()
+    }
+}
+
+fn decode_into<O, T>(msg: &[u8]) -> Result<T, postcard::Error>
+where
+    O: for<'a> Deserialize<'a> + Into<T>,
+{
+    Postcard.decode(msg).map(|o: O| o.into())
+}
+
+/// Codec for messages of type `T` prefixed with a version number
+///
+/// Values are always sent with the current version.
+/// Received messages of other versions are decoded using the matching upgrade,
+/// and fail with [`VersionError::UnknownVersion`] if there is none.
+///
+/// # Example
+/// ```rust
+/// use a653rs_postcard::prelude::*;
+/// # use a653rs::prelude::*;
+/// # use std::str::FromStr;
+/// # use std::time::Duration;
+/// # use mock::MockHyp as Hypervisor;
+/// # #[path = "../tests/mock.rs"]
+/// # mod mock;
+/// # Hypervisor::run_test(|mut ctx| {
+/// # let src_port = ctx
+/// #     .create_queuing_port_sender(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// # let port = ctx
+/// #     .create_queuing_port_receiver(Name::from_str("").unwrap(), 16, 10, QueuingDiscipline::Fifo)
+/// #     .unwrap();
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct StatusV1 {
+///     temperature: i16,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Status {
+///     temperature: i16,
+///     voltage: Option<u16>,
+/// }
+///
+/// impl From<StatusV1> for Status {
+///     fn from(old: StatusV1) -> Self {
+///         Status { temperature: old.temperature, voltage: None }
+///     }
+/// }
+///
+/// const UPGRADES: &[Upgrade<Status>] = &[Upgrade::from::<StatusV1>(1)];
+///
+/// let src_port: QueuingPortSender<Hypervisor> = src_port;
+/// let port: QueuingPortReceiver<Hypervisor> = port;
+/// let mut buf = [0; 16];
+/// // An outdated sender
+/// src_port
+///     .send_type_buf_with(&Versioned::new(1), StatusV1 { temperature: 21 }, SystemTime::Infinite, &mut buf)
+///     .unwrap();
+///
+/// let codec = Versioned::new(2).with_upgrades(UPGRADES);
+/// let (status, _) = port
+///     .recv_type_buf_with::<_, Status>(&codec, SystemTime::Infinite, &mut buf)
+///     .unwrap();
+/// assert_eq!(21, status.temperature);
+/// assert_eq!(None, status.voltage);
+/// # })
+/// ```
+pub struct Versioned<'u, T> {
+    version: u32,
+    upgrades: &'u [Upgrade<T>],
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<'u, T> Versioned<'u, T> {
+    /// Codec for messages of the current `version`
+    pub const fn new(version: u32) -> Self {
+        Self {
+            version,
+            upgrades: &[],
+            _type: PhantomData,
+        }
+    }
+
+    /// Decode messages of older versions using `upgrades`
+    pub const fn with_upgrades(mut self, upgrades: &'u [Upgrade<T>]) -> Self {
+        self.upgrades = upgrades;
+        self
+    }
+
+    /// Version of sent messages
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl<T> Clone for Versioned<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Versioned<'_, T> {}
+
+impl<T> fmt::Debug for Versioned<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Versioned")
+            .field("version", &self.version)
+            .field("upgrades", &self.upgrades)
+            .finish()
+    }
+}
+
+impl<T> Codec for Versioned<'_, T> {
+    type Error = VersionError;
+}
+
+impl<T: Serialize> Encode<T> for Versioned<'_, T> {
+    fn encode<'a>(&self, value: &T, buf: &'a mut [u8]) -> Result<&'a mut [u8], VersionError> {
+        let len = Postcard.encode(&(self.version, value), buf)?.len();
+        Ok(&mut buf[..len])
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> Decode<T> for Versioned<'_, T> {
+    fn decode(&self, msg: &[u8]) -> Result<T, VersionError> {
+        let (version, data) = postcard::take_from_bytes::<u32>(msg)?;
+        if version == self.version {
+            return Ok(Postcard.decode(data)?);
+        }
+        let upgrade = self
+            .upgrades
+            .iter()
+            .find(|u| u.version == version)
+            .ok_or(VersionError::UnknownVersion(version))?;
+        Ok((upgrade.decode)(data)?)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+
+    use a653rs::bindings::QueuingDiscipline;
+    use a653rs::prelude::{Name, SystemTime};
+    use mock::MockHyp;
+    use serde::{Deserialize, Serialize};
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        x: i32,
+        y: i32,
+        z: i32,
+    }
+
+    /// Version 1 only had two dimensions
+    #[derive(Serialize, Deserialize)]
+    struct PositionV1 {
+        x: i32,
+        y: i32,
+    }
+
+    impl From<PositionV1> for Position {
+        fn from(old: PositionV1) -> Self {
+            Position {
+                x: old.x,
+                y: old.y,
+                z: 0,
+            }
+        }
+    }
+
+    /// Version 2 counted in millimeters instead of meters
+    fn decode_v2(msg: &[u8]) -> Result<Position, postcard::Error> {
+        let p: Position = postcard::from_bytes(msg)?;
+        Ok(Position {
+            x: p.x / 1000,
+            y: p.y / 1000,
+            z: p.z / 1000,
+        })
+    }
+
+    const UPGRADES: &[Upgrade<Position>] = &[
+        Upgrade::from::<PositionV1>(1),
+        Upgrade {
+            version: 2,
+            decode: decode_v2,
+        },
+    ];
+
+    #[test]
+    fn version_upgrade() {
+        MockHyp::run_test(|mut ctx| {
+            let src_port = ctx
+                .create_queuing_port_sender(
+                    Name::from_str("").unwrap(),
+                    32,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let dest_port = ctx
+                .create_queuing_port_receiver(
+                    Name::from_str("").unwrap(),
+                    32,
+                    10,
+                    QueuingDiscipline::Fifo,
+                )
+                .unwrap();
+            let codec = Versioned::new(3).with_upgrades(UPGRADES);
+            let mut buf = [0; 32];
+
+            src_port
+                .send_type_buf_with(
+                    &Versioned::new(1),
+                    PositionV1 { x: 1, y: 2 },
+                    SystemTime::Infinite,
+                    &mut buf,
+                )
+                .unwrap();
+            src_port
+                .send_type_buf_with(
+                    &Versioned::new(2),
+                    Position {
+                        x: 3000,
+                        y: 4000,
+                        z: 5000,
+                    },
+                    SystemTime::Infinite,
+                    &mut buf,
+                )
+                .unwrap();
+            let current = Position { x: 6, y: 7, z: 8 };
+            src_port
+                .send_type_buf_with(&codec, current, SystemTime::Infinite, &mut buf)
+                .unwrap();
+            src_port
+                .send_type_buf_with(&Versioned::new(4), current, SystemTime::Infinite, &mut buf)
+                .unwrap();
+
+            for expected in [
+                Position { x: 1, y: 2, z: 0 },
+                Position { x: 3, y: 4, z: 5 },
+                current,
+            ] {
+                let (rec, _) = dest_port
+                    .recv_type_buf_with::<_, Position>(&codec, SystemTime::Infinite, &mut buf)
+                    .unwrap();
+                assert_eq!(expected, rec);
+            }
+            assert!(matches!(
+                dest_port.recv_type_buf_with::<_, Position>(&codec, SystemTime::Infinite, &mut buf),
+                Err(QueuingRecvBufError::Postcard(
+                    VersionError::UnknownVersion(4),
+                    _
+                ))
+            ));
+        })
+    }
+}
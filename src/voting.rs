@@ -0,0 +1,353 @@
+//! Voting across redundant sampling port destinations
+
+use core::cmp::Ordering;
+use core::ops::Sub;
+
+use a653rs::prelude::*;
+use serde::Deserialize;
+
+use crate::error::*;
+use crate::sampling::SamplingPortDestinationExt;
+
+/// Strategy for selecting a voted value from redundant samples
+pub trait VoteStrategy<T> {
+    /// Select the voted sample, returning its index
+    ///
+    /// `samples` contains one entry per channel,
+    /// `None` for channels which did not provide a valid sample.
+    fn select(&self, samples: &[Option<T>]) -> Option<usize>;
+
+    /// Whether `sample` agrees with the `voted` value
+    fn agrees(&self, voted: &T, sample: &T) -> bool;
+}
+
+/// Selects a value reported by more than half of all channels
+///
+/// Channels which provided no valid sample count as disagreeing,
+/// so that e.g. two out of three channels need to agree.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Majority;
+
+impl<T: PartialEq> VoteStrategy<T> for Majority {
+    fn select(&self, samples: &[Option<T>]) -> Option<usize> {
+        samples.iter().enumerate().find_map(|(i, s)| {
+            let s = s.as_ref()?;
+            let votes = samples.iter().flatten().filter(|o| *o == s).count();
+            (votes * 2 > samples.len()).then_some(i)
+        })
+    }
+
+    fn agrees(&self, voted: &T, sample: &T) -> bool {
+        voted == sample
+    }
+}
+
+/// Selects the median of all valid samples
+///
+/// For an even number of valid samples, the lower median is selected.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Median;
+
+impl<T: Ord> VoteStrategy<T> for Median {
+    fn select(&self, samples: &[Option<T>]) -> Option<usize> {
+        middle(samples, |a, b| Some(a.cmp(b)))
+    }
+
+    fn agrees(&self, voted: &T, sample: &T) -> bool {
+        voted == sample
+    }
+}
+
+/// Selects the middle value of all valid samples, e.g. for floating point sensor values
+///
+/// Samples which are not comparable to themselves (`NaN`) are discarded.
+/// For an even number of valid samples, the lower middle value is selected.
+/// Samples differing from the selected value by more than `tolerance` disagree.
+#[derive(Debug, Clone, Copy)]
+pub struct MidValue<T> {
+    /// Largest difference of an agreeing sample from the selected value
+    pub tolerance: T,
+}
+
+impl<T> MidValue<T> {
+    /// Mid-value select with the given `tolerance` for agreeing samples
+    pub fn new(tolerance: T) -> Self {
+        Self { tolerance }
+    }
+}
+
+impl<T: PartialOrd + Sub<Output = T> + Copy> VoteStrategy<T> for MidValue<T> {
+    fn select(&self, samples: &[Option<T>]) -> Option<usize> {
+        middle(samples, T::partial_cmp)
+    }
+
+    fn agrees(&self, voted: &T, sample: &T) -> bool {
+        let diff = if sample > voted {
+            *sample - *voted
+        } else {
+            *voted - *sample
+        };
+        diff <= self.tolerance
+    }
+}
+
+/// Index of the lower middle element of all comparable samples
+fn middle<T>(samples: &[Option<T>], cmp: impl Fn(&T, &T) -> Option<Ordering>) -> Option<usize> {
+    let comparable = |s: &T| cmp(s, s).is_some();
+    let count = samples.iter().flatten().filter(|s| comparable(s)).count();
+    if count == 0 {
+        return None;
+    }
+    // Without allocation, find the sample which has exactly as many samples below it
+    // as required for the lower middle position, accounting for equal samples.
+    let rank = (count - 1) / 2;
+    samples.iter().enumerate().find_map(|(i, s)| {
+        let s = s.as_ref().filter(|s| comparable(s))?;
+        let mut below = 0;
+        let mut equal_before = 0;
+        for (j, o) in samples.iter().enumerate() {
+            match o.as_ref().and_then(|o| cmp(o, s)) {
+                Some(Ordering::Less) => below += 1,
+                Some(Ordering::Equal) if j < i => equal_before += 1,
+                _ => {}
+            }
+        }
+        (below + equal_before == rank).then_some(i)
+    })
+}
+
+/// Outcome of a single channel during a vote
+#[derive(Debug, Clone)]
+pub enum ChannelStatus {
+    /// The sample agreed with the voted value
+    Agreed,
+    /// The sample disagreed with the voted value
+    Disagreed,
+    /// The sample was discarded due to [`Validity::Invalid`]
+    Invalid,
+    /// Receiving the sample failed
+    Apex(a653rs::prelude::Error),
+    /// The sample failed to deserialize
+    Postcard(postcard::Error),
+    /// The sample exceeded decode limits
+    Limit(LimitError),
+    /// The sample failed validation
+    Validation(ValidationError),
+}
+
+/// Successful vote
+#[derive(Debug, Clone)]
+pub struct Vote<T, const N: usize> {
+    /// Voted value
+    pub value: T,
+    /// Index of the channel providing `value`
+    pub channel: usize,
+    /// Diagnostics of every channel
+    pub status: [ChannelStatus; N],
+}
+
+impl<T, const N: usize> Vote<T, N> {
+    /// Whether any channel did not agree with the voted value
+    pub fn is_degraded(&self) -> bool {
+        self.status
+            .iter()
+            .any(|s| !matches!(s, ChannelStatus::Agreed))
+    }
+}
+
+/// Failed vote
+#[derive(Debug, Clone)]
+pub struct NoConsensus<const N: usize> {
+    /// Diagnostics of every channel
+    ///
+    /// Channels with a valid sample are reported as [`ChannelStatus::Disagreed`].
+    pub status: [ChannelStatus; N],
+}
+
+/// Voter reading a type from `N` redundant sampling port destinations
+#[derive(Debug)]
+pub struct Voter<V, S: ApexSamplingPortP4Ext, const N: usize> {
+    ports: [SamplingPortDestination<S>; N],
+    strategy: V,
+}
+
+impl<V, S: ApexSamplingPortP4Ext, const N: usize> Voter<V, S, N> {
+    /// Vote across `ports` using `strategy`
+    pub fn new(ports: [SamplingPortDestination<S>; N], strategy: V) -> Self {
+        Self { ports, strategy }
+    }
+
+    /// Underlying sampling ports
+    pub fn ports(&self) -> &[SamplingPortDestination<S>; N] {
+        &self.ports
+    }
+
+    /// Receive a type from every port and vote on the received values
+    ///
+    /// Requires a buffer `buf` for receiving and deserializing the data.
+    /// The buffer is reused for every port.
+    /// Samples with [`Validity::Invalid`] and samples which fail to be received
+    /// or deserialized are discarded.
+    ///
+    /// # Example
+    /// ```rust
+    /// use a653rs_postcard::prelude::*;
+    /// # use a653rs::prelude::*;
+    /// # use std::str::FromStr;
+    /// # use std::time::Duration;
+    /// # use mock::MockHyp as Hypervisor;
+    /// # #[path = "../tests/mock.rs"]
+    /// # mod mock;
+    /// # Hypervisor::run_test(|mut ctx| {
+    /// # let mut buf = [0; 500];
+    /// # let ports = ["A", "B", "C"].map(|name| {
+    /// #     let src = ctx
+    /// #         .create_sampling_port_source(Name::from_str(name).unwrap(), 500)
+    /// #         .unwrap();
+    /// #     src.send_type_buf(42u32, &mut buf).unwrap();
+    /// #     ctx.create_sampling_port_destination(Name::from_str(name).unwrap(), 500, Duration::ZERO)
+    /// #         .unwrap()
+    /// # });
+    ///
+    /// let ports: [SamplingPortDestination<Hypervisor>; 3] = ports;
+    /// let voter = Voter::new(ports, Majority);
+    /// let vote = voter.vote_buf::<u32>(&mut buf).unwrap();
+    /// assert_eq!(42, vote.value);
+    /// # })
+    /// ```
+    pub fn vote_buf<T>(&self, buf: &mut [u8]) -> Result<Vote<T, N>, NoConsensus<N>>
+    where
+        T: for<'a> Deserialize<'a>,
+        V: VoteStrategy<T>,
+    {
+        let mut status = [const { ChannelStatus::Agreed }; N];
+        let mut samples: [Option<T>; N] = [const { None }; N];
+        for (i, port) in self.ports.iter().enumerate() {
+            match port.recv_type_buf::<T>(buf) {
+                Ok((Validity::Valid, t)) => samples[i] = Some(t),
+                Ok((Validity::Invalid, _)) => status[i] = ChannelStatus::Invalid,
+                Err(SamplingRecvBufError::Apex(e)) => status[i] = ChannelStatus::Apex(e),
+                Err(SamplingRecvBufError::Postcard(e, _, _)) => {
+                    status[i] = ChannelStatus::Postcard(e)
+                }
+                Err(SamplingRecvBufError::Limit(e, _, _)) => status[i] = ChannelStatus::Limit(e),
+                Err(SamplingRecvBufError::Validation(e, _, _)) => {
+                    status[i] = ChannelStatus::Validation(e)
+                }
+            }
+        }
+
+        let Some(channel) = self.strategy.select(&samples) else {
+            for (status, sample) in status.iter_mut().zip(&samples) {
+                if sample.is_some() {
+                    *status = ChannelStatus::Disagreed;
+                }
+            }
+            return Err(NoConsensus { status });
+        };
+
+        // Only indices of valid samples are selected
+        let value = samples[channel].take().unwrap();
+        for (status, sample) in status.iter_mut().zip(&samples) {
+            if let Some(sample) = sample {
+                if !self.strategy.agrees(&value, sample) {
+                    *status = ChannelStatus::Disagreed;
+                }
+            }
+        }
+        Ok(Vote {
+            value,
+            channel,
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests"]
+mod tests {
+    use core::str::FromStr;
+    use core::time::Duration;
+
+    use a653rs::bindings::Validity;
+    use a653rs::prelude::{Name, SamplingPortDestination, StartContext};
+    use mock::MockHyp;
+
+    use crate::prelude::*;
+
+    extern crate std;
+
+    #[allow(clippy::duplicate_mod)]
+    mod mock;
+
+    fn triplex<T: serde::Serialize>(
+        ctx: &mut StartContext<MockHyp>,
+        values: [T; 3],
+    ) -> [SamplingPortDestination<MockHyp>; 3] {
+        let mut buf = [0; 100];
+        let mut names = ["A", "B", "C"].into_iter();
+        values.map(|value| {
+            let name = Name::from_str(names.next().unwrap()).unwrap();
+            let src = ctx.create_sampling_port_source(name.clone(), 100).unwrap();
+            src.send_type_buf(value, &mut buf).unwrap();
+            ctx.create_sampling_port_destination(name, 100, Duration::ZERO)
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn vote_majority() {
+        MockHyp::run_test(|mut ctx| {
+            let voter = Voter::new(triplex(&mut ctx, [1u32, 2, 1]), Majority);
+            let mut buf = [0; 100];
+
+            let vote = voter.vote_buf::<u32>(&mut buf).unwrap();
+            assert_eq!(1, vote.value);
+            assert!(matches!(vote.status[1], ChannelStatus::Disagreed));
+            assert!(vote.is_degraded());
+
+            MockHyp::set_validity(voter.ports()[0].id(), Validity::Invalid);
+            let err = voter.vote_buf::<u32>(&mut buf).unwrap_err();
+            assert!(matches!(err.status[0], ChannelStatus::Invalid));
+            assert!(matches!(err.status[1], ChannelStatus::Disagreed));
+        })
+    }
+
+    #[test]
+    fn vote_median() {
+        MockHyp::run_test(|mut ctx| {
+            let voter = Voter::new(triplex(&mut ctx, [7i32, -3, 5]), Median);
+            let mut buf = [0; 100];
+
+            let vote = voter.vote_buf::<i32>(&mut buf).unwrap();
+            assert_eq!(5, vote.value);
+            assert_eq!(2, vote.channel);
+
+            let vote = voter.vote_buf::<bool>(&mut buf);
+            assert!(matches!(vote, Err(NoConsensus { .. })));
+        })
+    }
+
+    #[test]
+    fn vote_mid_value() {
+        MockHyp::run_test(|mut ctx| {
+            let voter = Voter::new(triplex(&mut ctx, [1.0f32, 1.05, 9.0]), MidValue::new(0.1));
+            let mut buf = [0; 100];
+
+            let vote = voter.vote_buf::<f32>(&mut buf).unwrap();
+            assert_eq!(1.05, vote.value);
+            assert!(matches!(vote.status[0], ChannelStatus::Agreed));
+            assert!(matches!(vote.status[2], ChannelStatus::Disagreed));
+        })
+    }
+
+    #[test]
+    fn middle_with_duplicates() {
+        assert_eq!(Some(1), Median.select(&[Some(1), Some(1), Some(1)]));
+        assert_eq!(Some(0), Median.select(&[Some(2), Some(1), None, Some(3)]));
+        assert_eq!(
+            Some(0),
+            MidValue::new(0.0).select(&[Some(2.0), Some(f32::NAN), Some(2.0)])
+        );
+    }
+}
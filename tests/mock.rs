@@ -1,14 +1,28 @@
 use core::mem::MaybeUninit;
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::string::String;
 use std::sync::Mutex;
 use std::vec::Vec;
 
-use a653rs::bindings::{ApexQueuingPortP4, ApexSamplingPortP4, Validity};
+use a653rs::bindings::{
+    ApexQueuingPortP4, ApexSamplingPortP4, ApexSystemTime, ApexTimeP1, ApexTimeP4, ErrorReturnCode,
+    Validity,
+};
 use a653rs::prelude::StartContext;
 
 extern crate std;
 
-static mut SAMPLING_PORTS: Vec<u8> = Vec::new();
-static mut QUEUING_PORTS: Vec<Vec<u8>> = Vec::new();
+/// A channel connecting all ports created with the same name
+struct Channel {
+    name: String,
+    sampling: Vec<u8>,
+    validity: Validity,
+    queue: VecDeque<Vec<u8>>,
+}
+
+static CHANNELS: Mutex<Vec<Channel>> = Mutex::new(Vec::new());
+static TIME: Mutex<Duration> = Mutex::new(Duration::ZERO);
 static SYNC: Mutex<()> = Mutex::new(());
 
 pub struct MockHyp;
@@ -16,80 +30,124 @@ pub struct MockHyp;
 impl MockHyp {
     /// Prevents multiple tests from running concurrently
     /// Also clears all ports before starting with the next one
+    #[allow(dead_code)]
     pub fn run_test(t: fn(StartContext<MockHyp>)) {
+        Self::run_test_as(t)
+    }
+
+    /// Like [`MockHyp::run_test`], with the start context of a hypervisor wrapping the mock
+    pub fn run_test_as<H>(t: fn(StartContext<H>)) {
         let ctx = unsafe { MaybeUninit::zeroed().assume_init() };
-        let lock = SYNC.lock();
-        unsafe {
-            SAMPLING_PORTS.clear();
-            QUEUING_PORTS.clear();
-        }
+        let lock = SYNC.lock().unwrap_or_else(|e| e.into_inner());
+        CHANNELS.lock().unwrap().clear();
+        *TIME.lock().unwrap() = Duration::ZERO;
         t(ctx);
         drop(lock);
     }
+
+    /// Sets the [`Validity`] reported for the sampling port with the given id
+    #[allow(dead_code)]
+    pub fn set_validity(id: i64, validity: Validity) {
+        CHANNELS.lock().unwrap()[id as usize].validity = validity;
+    }
+
+    /// Advances the system time returned by the mock
+    #[allow(dead_code)]
+    pub fn advance(time: Duration) {
+        *TIME.lock().unwrap() += time;
+    }
+
+    fn open(name: &[u8]) -> i64 {
+        let name: String = name
+            .iter()
+            .take_while(|b| **b != 0)
+            .map(|b| *b as char)
+            .collect();
+        let mut channels = CHANNELS.lock().unwrap();
+        if let Some(id) = channels.iter().position(|c| c.name == name) {
+            return id as i64;
+        }
+        channels.push(Channel {
+            name,
+            sampling: Vec::new(),
+            validity: Validity::Valid,
+            queue: VecDeque::new(),
+        });
+        channels.len() as i64 - 1
+    }
 }
 
 impl ApexSamplingPortP4 for MockHyp {
     fn create_sampling_port(
-        _sampling_port_name: a653rs::bindings::SamplingPortName,
+        sampling_port_name: a653rs::bindings::SamplingPortName,
         _max_message_size: a653rs::prelude::MessageSize,
         _port_direction: a653rs::bindings::PortDirection,
         _refresh_period: a653rs::bindings::ApexSystemTime,
     ) -> Result<a653rs::prelude::SamplingPortId, a653rs::bindings::ErrorReturnCode> {
-        Ok(0)
+        Ok(Self::open(&sampling_port_name))
     }
 
     fn write_sampling_message(
-        _sampling_port_id: a653rs::prelude::SamplingPortId,
+        sampling_port_id: a653rs::prelude::SamplingPortId,
         message: &[a653rs::prelude::ApexByte],
     ) -> Result<(), a653rs::bindings::ErrorReturnCode> {
-        unsafe { SAMPLING_PORTS = message.to_vec() };
+        CHANNELS.lock().unwrap()[sampling_port_id as usize].sampling = message.to_vec();
         Ok(())
     }
 
     unsafe fn read_sampling_message(
-        _sampling_port_id: a653rs::prelude::SamplingPortId,
+        sampling_port_id: a653rs::prelude::SamplingPortId,
         out: &mut [a653rs::prelude::ApexByte],
     ) -> Result<
         (a653rs::prelude::Validity, a653rs::prelude::MessageSize),
         a653rs::bindings::ErrorReturnCode,
     > {
-        let msg = unsafe { SAMPLING_PORTS.clone() };
-        let len = out.len().min(msg.len());
-        out[..len].copy_from_slice(&msg.as_slice()[..len]);
+        let channels = CHANNELS.lock().unwrap();
+        let channel = &channels[sampling_port_id as usize];
+        if channel.sampling.is_empty() {
+            return Err(ErrorReturnCode::NoAction);
+        }
+        let len = out.len().min(channel.sampling.len());
+        out[..len].copy_from_slice(&channel.sampling[..len]);
 
-        Ok((Validity::Valid, len as u32))
+        Ok((channel.validity, len as u32))
     }
 }
 
 impl ApexQueuingPortP4 for MockHyp {
     fn create_queuing_port(
-        _queuing_port_name: a653rs::bindings::QueuingPortName,
+        queuing_port_name: a653rs::bindings::QueuingPortName,
         _max_message_size: a653rs::prelude::MessageSize,
         _max_nb_message: a653rs::prelude::MessageRange,
         _port_direction: a653rs::bindings::PortDirection,
         _queuing_discipline: a653rs::prelude::QueuingDiscipline,
     ) -> Result<a653rs::prelude::QueuingPortId, a653rs::bindings::ErrorReturnCode> {
-        Ok(0)
+        Ok(Self::open(&queuing_port_name))
     }
 
     fn send_queuing_message(
-        _queuing_port_id: a653rs::prelude::QueuingPortId,
+        queuing_port_id: a653rs::prelude::QueuingPortId,
         message: &[a653rs::prelude::ApexByte],
         _time_out: a653rs::bindings::ApexSystemTime,
     ) -> Result<(), a653rs::bindings::ErrorReturnCode> {
-        unsafe { QUEUING_PORTS.push(message.to_vec()) };
+        CHANNELS.lock().unwrap()[queuing_port_id as usize]
+            .queue
+            .push_back(message.to_vec());
         Ok(())
     }
 
     unsafe fn receive_queuing_message(
-        _queuing_port_id: a653rs::prelude::QueuingPortId,
+        queuing_port_id: a653rs::prelude::QueuingPortId,
         _time_out: a653rs::bindings::ApexSystemTime,
         out: &mut [a653rs::prelude::ApexByte],
     ) -> Result<
         (a653rs::prelude::MessageSize, a653rs::prelude::QueueOverflow),
         a653rs::bindings::ErrorReturnCode,
     > {
-        let msg = unsafe { QUEUING_PORTS.remove(0) };
+        let msg = CHANNELS.lock().unwrap()[queuing_port_id as usize]
+            .queue
+            .pop_front()
+            .ok_or(ErrorReturnCode::NotAvailable)?;
         let len = out.len().min(msg.len());
         out[..len].copy_from_slice(&msg.as_slice()[..len]);
 
@@ -108,3 +166,24 @@ impl ApexQueuingPortP4 for MockHyp {
         unimplemented!()
     }
 }
+
+impl ApexTimeP4 for MockHyp {
+    fn periodic_wait() -> Result<(), a653rs::bindings::ErrorReturnCode> {
+        unimplemented!()
+    }
+
+    fn get_time() -> ApexSystemTime {
+        TIME.lock().unwrap().as_nanos() as ApexSystemTime
+    }
+}
+
+impl ApexTimeP1 for MockHyp {
+    fn timed_wait(delay_time: ApexSystemTime) -> Result<(), a653rs::bindings::ErrorReturnCode> {
+        *TIME.lock().unwrap() += Duration::from_nanos(delay_time as u64);
+        Ok(())
+    }
+
+    fn replenish(_budget_time: ApexSystemTime) -> Result<(), a653rs::bindings::ErrorReturnCode> {
+        unimplemented!()
+    }
+}